@@ -0,0 +1,52 @@
+//! Punches small holes into a terrain mask, retracing only the rings around each of them, and
+//! compares with tracing the whole mask again, `cargo bench --bench incremental`.
+
+use marching_pixels::{Algorithm, Args, BitGrid, Incremental, Winding};
+use std::{hint::black_box, time::Instant};
+
+const SIZE: usize = 2048;
+const HOLE: usize = 16;
+const ITERATIONS: u32 = 200;
+
+fn main() {
+    // Rolling hills over caves, like the terrain of a destructible world.
+    let mut mask = BitGrid::new(SIZE, SIZE);
+    for x in 0..SIZE {
+        let ground = SIZE / 3 + (x * 7 % 200) + (x / 61 % 5) * 40;
+        mask.fill_rect([x, ground], [1, SIZE - ground], true);
+    }
+    let coordinate = |value: usize| f32::from(u16::try_from(value).unwrap());
+    for index in 0..400 {
+        let center = [index * 811 % SIZE, SIZE / 2 + index * 487 % (SIZE / 2)].map(coordinate);
+        mask.fill_circle(center, coordinate(10 + index % 5 * 6), false);
+    }
+
+    let mut incremental = Incremental::<u32>::default();
+    let start = Instant::now();
+    incremental.reset(&mask);
+    println!(
+        "   reset: {:>10.2?} per {SIZE}x{SIZE} mask",
+        start.elapsed()
+    );
+
+    let hole = [false; HOLE * HOLE];
+    let start = Instant::now();
+    for index in 0..ITERATIONS as usize {
+        let origin = [
+            index * 397 % (SIZE - HOLE),
+            SIZE / 3 + index * 211 % (SIZE / 2),
+        ];
+        black_box(incremental.update(origin, Args::new(HOLE, HOLE, hole)));
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    println!("  update: {elapsed:>10.2?} per {HOLE}x{HOLE} hole");
+
+    let mut algorithm = Algorithm::<u32>::default();
+    let start = Instant::now();
+    let shapes = algorithm.shapes(incremental.pixels(), Winding::CounterClockwise);
+    println!(
+        "  shapes: {:>10.2?} per {SIZE}x{SIZE} mask",
+        start.elapsed()
+    );
+    assert_eq!(shapes.rings().count(), incremental.contours().count());
+}
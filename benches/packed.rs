@@ -0,0 +1,53 @@
+//! Labels the same mask from `bool` pixels and from packed words, `cargo bench --bench packed`.
+
+use marching_pixels::core::{self, Cell, Connectivity, Packed};
+use std::{hint::black_box, time::Instant};
+
+const SIZE: usize = 4096;
+const ITERATIONS: u32 = 10;
+
+/// Labels the whole mask into the cells.
+type Set<'a> = Box<dyn FnMut(&mut [Cell<u32>]) + 'a>;
+
+fn main() {
+    // Blobs with noisy edges, mostly runs of equal pixels like real masks.
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let pixels: Vec<bool> = (0..SIZE * SIZE)
+        .map(|index| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let (x, y) = (index % SIZE, index / SIZE);
+            ((x / 97 + y / 61) % 3 == 0) != state.is_multiple_of(64)
+        })
+        .collect();
+    let stride = SIZE.div_ceil(64);
+    let mut words = vec![0; stride * SIZE];
+    for (index, _) in pixels.iter().enumerate().filter(|(_, &pixel)| pixel) {
+        let (x, y) = (index % SIZE, index / SIZE);
+        words[y * stride + x / 64] |= 1 << (x % 64);
+    }
+
+    let mut cells = vec![Cell::<u32>::default(); core::capacity(SIZE, SIZE)];
+    let mut bench = |name: &str, mut set: Set| {
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            core::clear(&mut cells);
+            set(&mut cells);
+            black_box(&cells);
+        }
+        let elapsed = start.elapsed() / ITERATIONS;
+        let per_pixel = elapsed.as_secs_f64() * 1e9 / (SIZE * SIZE) as f64;
+        println!("{name:>8}: {elapsed:>10.2?} per {SIZE}x{SIZE} mask, {per_pixel:.2} ns per pixel");
+    };
+    bench(
+        "bool",
+        Box::new(|cells| core::set(cells, SIZE, pixels.iter().copied(), Connectivity::Four)),
+    );
+    bench(
+        "packed",
+        Box::new(|cells| {
+            core::set_packed(cells, Packed::new(SIZE, SIZE, &words), Connectivity::Four);
+        }),
+    );
+}
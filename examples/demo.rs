@@ -70,26 +70,22 @@ impl ShapeMask {
             pixels: self.0.into_iter().flatten(),
         };
         let mut algorithm = marching_pixels::Algorithm::with_capacity(Self::WIDTH, Self::HEIGHT);
-        let (vertices, indices) = algorithm.search(args);
-        vertices
-            .clone()
-            .enumerate()
-            .for_each(|(index, vertex)| info!(?vertex, ?index));
-        indices.clone().for_each(|[from, to]| info!(?from, ?to));
-        let vertices: Vec<Vec2> = vertices.map(|[x, y]| vec2(x as _, y as _)).collect();
-
-        let indices: Vec<[u32; 2]> = indices.map(|[x, y]| [x as _, y as _]).collect();
-        Some(Collider::convex_decomposition_with_params(
-            &vertices,
-            &indices,
-            &VHACDParameters {
-                fill_mode: FillMode::FloodFill {
-                    detect_cavities: true,
-                    detect_self_intersections: true,
-                },
-                ..Default::default()
-            },
-        ))
+        let shapes = algorithm.shapes(args, marching_pixels::Winding::CounterClockwise);
+        let pieces = shapes.convex_decomposition(None);
+        info!(pieces = pieces.len());
+        let pieces = pieces
+            .iter()
+            .filter_map(|piece| {
+                let points: Vec<Vec2> = piece
+                    .iter()
+                    .map(|&index| shapes.vertices[index as usize])
+                    .map(|[x, y]| vec2(x as _, y as _))
+                    .collect();
+                Collider::convex_polyline(points)
+            })
+            .map(|collider| (Vec2::ZERO, 0.0, collider))
+            .collect();
+        Some(Collider::compound(pieces))
     }
 }
 
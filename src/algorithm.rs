@@ -5,41 +5,412 @@ mod bevy_args;
 #[cfg_attr(doc, doc(cfg(feature = "image")))]
 mod image_args;
 
-use crate::core::{self, Cell, HorizontalIndices, VerticalIndices, Vertices};
-use ::alloc::vec::Vec;
-use ::core::iter;
+use crate::{
+    chamfer,
+    components::Components,
+    core::{
+        self, levels, Bits, Cell, Connectivity, Error, HorizontalIndices, Packed, Primitive,
+        VerticalIndices, Vertices,
+    },
+    hierarchy::Hierarchy,
+    iso::{self, Isobands},
+    shapes::{self, Shapes, Traced, Winding},
+    tiles::Tile,
+};
+use ::alloc::vec::{self, Vec};
+use ::core::{
+    iter::{self, FusedIterator},
+    mem,
+};
 
+type Indices<'a, P> = iter::Chain<HorizontalIndices<'a, P>, VerticalIndices<'a, P>>;
+
+/// Reusable buffer of cells, [`u16`] ones by default, `Algorithm::<u32>::default()` or
+/// `Algorithm::<u64>::default()` for larger images.
 #[derive(Clone, Debug, Default)]
-pub struct Algorithm(Vec<Cell>);
+pub struct Algorithm<P = u16> {
+    cells: Vec<Cell<P>>,
+    connectivity: Connectivity,
+    max_cells: Option<usize>,
+    /// Pixels collected to be split between threads.
+    #[cfg(feature = "rayon")]
+    pixels: Vec<bool>,
+}
 
 impl Algorithm {
     #[must_use]
     pub const fn new() -> Self {
-        Self(Vec::new())
+        Self {
+            cells: Vec::new(),
+            connectivity: Connectivity::Four,
+            max_cells: None,
+            #[cfg(feature = "rayon")]
+            pixels: Vec::new(),
+        }
     }
 
     #[must_use]
     pub fn with_capacity(width: usize, height: usize) -> Self {
-        Self(Vec::with_capacity(core::capacity(width, height)))
+        let mut algorithm = Self::new();
+        algorithm.reserve(width, height);
+        algorithm
+    }
+}
+
+impl<P: Primitive> Algorithm<P> {
+    /// Makes room for images of up to `width * height` pixels.
+    pub fn reserve(&mut self, width: usize, height: usize) {
+        let additional = core::capacity(width, height).saturating_sub(self.cells.len());
+        self.cells.reserve(additional);
+    }
+
+    #[must_use]
+    pub const fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.connectivity = connectivity;
+        self
     }
 
-    pub fn search(
+    #[must_use]
+    pub const fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    /// Makes the `try_` methods fail before allocating more than `max_cells` cells, one per
+    /// pixel corner, so that the dimensions of an untrusted image cannot exhaust memory.
+    #[must_use]
+    pub const fn with_max_cells(mut self, max_cells: usize) -> Self {
+        self.max_cells = Some(max_cells);
+        self
+    }
+
+    #[must_use]
+    pub const fn max_cells(&self) -> Option<usize> {
+        self.max_cells
+    }
+
+    fn resize(&mut self, new_len: usize) {
+        let old_len = self.cells.len();
+        self.cells.resize(new_len, Cell::default());
+        core::clear(&mut self.cells[..old_len.min(new_len)]);
+    }
+
+    /// Returns the width and the origin of `args`.
+    fn set(&mut self, args: impl IntoArgs) -> (usize, [usize; 2]) {
+        let origin = args.origin();
+        if let Some(packed) = args.packed() {
+            self.resize(core::capacity(packed.width, packed.height));
+            core::set_packed(&mut self.cells, packed, self.connectivity);
+            return (packed.width, origin);
+        }
+        let args = args.into_args();
+        self.resize(core::capacity(args.width, args.height));
+        let pixels = args.pixels.into_iter().take(args.width * args.height);
+        #[cfg(feature = "rayon")]
+        {
+            self.pixels.clear();
+            self.pixels.extend(pixels);
+            core::par_set(&mut self.cells, args.width, &self.pixels, self.connectivity);
+        }
+        #[cfg(not(feature = "rayon"))]
+        core::set(&mut self.cells, args.width, pixels, self.connectivity);
+        (args.width, origin)
+    }
+
+    /// Same as [`Self::set()`], but checks the cell limit and the pixel count hint before
+    /// allocating any cells.
+    fn try_set(&mut self, args: impl IntoArgs) -> Result<(usize, [usize; 2]), Error> {
+        let origin = args.origin();
+        let Args {
+            width,
+            height,
+            pixels,
+        } = args.into_args();
+        let pixels = pixels.into_iter();
+        let new_len = core::checked_capacity(width, height)
+            .filter(|_| {
+                let max = origin[0]
+                    .saturating_add(width)
+                    .max(origin[1].saturating_add(height));
+                max <= P::MAX.into_usize()
+            })
+            .ok_or(Error::DimensionsOverflow { width, height })?;
+        let expected = width * height;
+        match pixels.size_hint() {
+            (_, Some(len)) if len < expected => return Err(Error::PixelsTooFew { len, expected }),
+            (len, _) if len > expected => return Err(Error::PixelsTooMany { expected }),
+            _ => {}
+        }
+        if let Some(max) = self.max_cells.filter(|&max| new_len > max) {
+            return Err(Error::CellsTooMany { len: new_len, max });
+        }
+        self.cells
+            .try_reserve(new_len.saturating_sub(self.cells.len()))
+            .map_err(|_| Error::AllocationFailed { len: new_len })?;
+        self.resize(new_len);
+        core::try_set(&mut self.cells, width, height, pixels, self.connectivity)?;
+        Ok((width, origin))
+    }
+
+    fn trace_cells(&mut self, width: usize, origin: [usize; 2]) -> Traced<P> {
+        let vertices = core::get(&self.cells, width)
+            .0
+            .with_origin(origin)
+            .collect();
+        let rings = shapes::collect_rings(core::trace(&mut self.cells, width));
+        (vertices, rings)
+    }
+
+    fn trace(&mut self, args: impl IntoArgs) -> Traced<P> {
+        let (width, origin) = self.set(args);
+        self.trace_cells(width, origin)
+    }
+
+    fn try_trace(&mut self, args: impl IntoArgs) -> Result<Traced<P>, Error> {
+        let (width, origin) = self.try_set(args)?;
+        Ok(self.trace_cells(width, origin))
+    }
+
+    /// Traces `pixels` of samples at or above `level`.
+    fn trace_level(
+        &mut self,
+        args: Args<&[f32]>,
+        level: f32,
+        pixels: impl IntoIterator<Item = bool>,
+    ) -> Traced<P> {
+        let Args { width, height, .. } = args;
+        self.set(Args::new(width, height, pixels));
+        if self.connectivity == Connectivity::Average {
+            core::connect(&mut self.cells, width, |vertex| {
+                is_average_filled(args, level, vertex)
+            });
+        }
+        self.trace_cells(width, [0, 0])
+    }
+
+    /// Finds vertices of pixel outlines and the edges between them.
+    ///
+    /// # Panics
+    /// If there are more vertices than `P` can index, see [`core::set()`]
+    pub fn search(&mut self, args: impl IntoArgs) -> (Vertices<'_, P>, Indices<'_, P>) {
+        let (width, origin) = self.set(args);
+        let (vertices, horizontal_indices, vertical_indices) = core::get(&self.cells, width);
+        (
+            vertices.with_origin(origin),
+            horizontal_indices.chain(vertical_indices),
+        )
+    }
+
+    /// Same as [`Self::search()`], but with edges found by rows and columns in parallel, in the
+    /// same order once collected.
+    ///
+    /// # Panics
+    /// If there are more vertices than `P` can index, see [`core::set()`]
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(doc, doc(cfg(feature = "rayon")))]
+    pub fn par_search(
         &mut self,
         args: impl IntoArgs,
-    ) -> (Vertices, iter::Chain<HorizontalIndices, VerticalIndices>) {
-        let args = args.into_args();
-        let old_len = self.0.len();
-        let new_len = core::capacity(args.width, args.height);
-        self.0.resize(new_len, Cell::EMPTY);
-        core::clear(&mut self.0[..old_len.min(new_len)]);
-        core::set(
-            &mut self.0,
-            args.width,
-            args.pixels.into_iter().take(args.width * args.height),
+    ) -> (
+        Vertices<'_, P>,
+        impl rayon::iter::ParallelIterator<Item = [P; 2]> + '_,
+    ) {
+        let (width, origin) = self.set(args);
+        let vertices = core::get(&self.cells, width).0.with_origin(origin);
+        (vertices, core::par_indices(&self.cells, width))
+    }
+
+    /// Same as [`Self::search()`], but fails instead of panicking, wrapping indices or ignoring
+    /// extra pixels, so it is safe to run on untrusted images once their size is limited with
+    /// [`Self::with_max_cells()`].
+    ///
+    /// # Errors
+    /// See [`Error`]
+    pub fn try_search(
+        &mut self,
+        args: impl IntoArgs,
+    ) -> Result<(Vertices<'_, P>, Indices<'_, P>), Error> {
+        let (width, origin) = self.try_set(args)?;
+        let (vertices, horizontal_indices, vertical_indices) = core::get(&self.cells, width);
+        Ok((
+            vertices.with_origin(origin),
+            horizontal_indices.chain(vertical_indices),
+        ))
+    }
+
+    /// Same as [`Self::search()`], but with edges joined into outer rings owning their holes.
+    ///
+    /// # Panics
+    /// If there are more vertices than `P` can index, see [`core::set()`]
+    pub fn shapes(&mut self, args: impl IntoArgs, winding: Winding) -> Shapes<P, P> {
+        let (vertices, rings) = self.trace(args);
+        Shapes::new(vertices, rings, winding)
+    }
+
+    /// Same as [`Self::shapes()`], but fails like [`Self::try_search()`].
+    ///
+    /// # Errors
+    /// See [`Error`]
+    pub fn try_shapes(
+        &mut self,
+        args: impl IntoArgs,
+        winding: Winding,
+    ) -> Result<Shapes<P, P>, Error> {
+        let (vertices, rings) = self.try_trace(args)?;
+        Ok(Shapes::new(vertices, rings, winding))
+    }
+
+    /// Same as [`Self::shapes()`], but with corners cut diagonally through the middles of
+    /// pixel sides, like classic marching squares over pixel centers.
+    ///
+    /// # Panics
+    /// If there are more vertices or chamfered vertices than `P` can index
+    pub fn chamfered_shapes(&mut self, args: impl IntoArgs, winding: Winding) -> Shapes<f32, P> {
+        chamfer::chamfer(&self.shapes(args, winding))
+    }
+
+    /// Same as [`Self::search()`], but with corners cut like [`Self::chamfered_shapes()`],
+    /// every edge going from one vertex to the next around its ring.
+    ///
+    /// # Panics
+    /// If there are more vertices or chamfered vertices than `P` can index
+    pub fn chamfered_search(
+        &mut self,
+        args: impl IntoArgs,
+    ) -> (vec::IntoIter<[f32; 2]>, impl Iterator<Item = [P; 2]>) {
+        let Shapes { vertices, shapes } = self.chamfered_shapes(args, Winding::CounterClockwise);
+        (vertices.into_iter(), chamfer::edges(shapes))
+    }
+
+    /// Contours a scalar field at `iso` with vertices interpolated between samples, samples at
+    /// or above `iso` are treated as filled pixels.
+    ///
+    /// # Panics
+    /// If there are less than `width * height` samples, or more vertices than `P` can index
+    pub fn iso_shapes(&mut self, args: Args<&[f32]>, iso: f32, winding: Winding) -> Shapes<f32, P> {
+        let pixels = args.pixels.iter().map(|&sample| sample >= iso);
+        let traced = self.trace_level(args, iso, pixels);
+        iso::iso_shapes(
+            &traced,
+            iso,
+            [args.width, args.height],
+            args.pixels,
+            winding,
+        )
+    }
+
+    /// Contours a scalar field at every one of ascending `levels` and between each adjacent pair
+    /// of them. Samples are sorted into bands and every level is labelled in a single walk over
+    /// the grid, then rings of every level are nested together once.
+    ///
+    /// # Panics
+    /// If there are less than `width * height` samples, `levels` are not ascending, or there are
+    /// more vertices than `P` can index
+    pub fn isobands(
+        &mut self,
+        args: Args<&[f32]>,
+        levels: &[f32],
+        winding: Winding,
+    ) -> Isobands<P> {
+        let Args {
+            width,
+            height,
+            pixels: samples,
+        } = args;
+        assert!(
+            levels.windows(2).all(|pair| pair[0] <= pair[1]),
+            "levels not ascending"
+        );
+        let bands: Vec<usize> = samples
+            .iter()
+            .take(width * height)
+            .map(|&sample| levels.partition_point(|&level| level <= sample))
+            .collect();
+        let is_joined = |index: usize, vertex| is_average_filled(args, levels[index], vertex);
+        let traced: Vec<Traced<P>> = levels::set(
+            &bands,
+            [width, height],
+            levels.len(),
+            self.connectivity,
+            is_joined,
+        )
+        .into_iter()
+        .map(|level| {
+            let (vertices, ring_vertices) = level.trace();
+            (vertices, shapes::collect_rings(ring_vertices))
+        })
+        .collect();
+        iso::isobands(&traced, levels, [width, height], samples, winding)
+    }
+
+    /// Extracts the tile at `index` of a grid of `size` tiles, to be joined with its neighbours
+    /// by [`crate::Tiles`]. Coordinates are in the whole image.
+    ///
+    /// # Panics
+    /// If the tile is larger than `size`, or there are more vertices than `P` can index.
+    pub fn tile(
+        &mut self,
+        args: Args<impl IntoIterator<Item = bool>>,
+        size: [usize; 2],
+        index: [usize; 2],
+    ) -> Tile<P> {
+        assert!(
+            args.width <= size[0] && args.height <= size[1],
+            "tile larger than the grid"
         );
-        let (vertices, horizontal_indices, vertical_indices) = core::get(&self.0, args.width);
-        (vertices, horizontal_indices.chain(vertical_indices))
+        let tile_size = [args.width, args.height];
+        let traced = self.trace(args);
+        Tile::new(traced, tile_size, size, index, self.connectivity)
     }
+
+    /// Same as [`Self::shapes()`], but keeps the whole nesting of rings.
+    ///
+    /// # Panics
+    /// If there are more vertices than `P` can index, see [`core::set()`]
+    pub fn hierarchy(&mut self, args: impl IntoArgs, winding: Winding) -> Hierarchy<P> {
+        let (vertices, rings) = self.trace(args);
+        Hierarchy::new(vertices, rings, winding)
+    }
+
+    /// Same as [`Self::shapes()`], but with every shape being a separate component with its
+    /// pixel count and bounding box, and every pixel labelled with its component.
+    ///
+    /// # Panics
+    /// If there are more vertices than `P` can index, see [`core::set()`]
+    pub fn components(&mut self, args: impl IntoArgs, winding: Winding) -> Components<P> {
+        let (width, origin) = self.set(args);
+        let height = (self.cells.len() / (width + 1)).saturating_sub(1);
+        let (vertices, rings) = self.trace_cells(width, origin);
+        Components::new(
+            Shapes::new(vertices, rings, winding),
+            [width, height],
+            origin,
+        )
+    }
+
+    /// Same as [`Self::hierarchy()`], but fails like [`Self::try_search()`].
+    ///
+    /// # Errors
+    /// See [`Error`]
+    pub fn try_hierarchy(
+        &mut self,
+        args: impl IntoArgs,
+        winding: Winding,
+    ) -> Result<Hierarchy<P>, Error> {
+        let (vertices, rings) = self.try_trace(args)?;
+        Ok(Hierarchy::new(vertices, rings, winding))
+    }
+}
+
+/// Whether the average of the four samples around the saddle at `[x, y]` is at or above `level`.
+fn is_average_filled(args: Args<&[f32]>, level: f32, [x, y]: [usize; 2]) -> bool {
+    let Args {
+        width,
+        pixels: samples,
+        ..
+    } = args;
+    let (top, bottom) = ((y - 1) * width + x, y * width + x);
+    let sum = samples[top - 1] + samples[top] + samples[bottom - 1] + samples[bottom];
+    sum >= 4.0 * level
 }
 
 #[derive(Copy, Clone)]
@@ -59,13 +430,42 @@ impl<P> Args<P> {
     }
 }
 
-pub trait IntoArgs {
-    type Pixels: IntoIterator<Item = bool>;
+/// Image of `width * height` pixels row by row, filled or not by default, or of any other
+/// kind of pixel like the labels of [`crate::Boundaries`].
+pub trait IntoArgs<T = bool> {
+    type Pixels: IntoIterator<Item = T>;
 
     fn into_args(self) -> Args<Self::Pixels>;
+
+    /// Offset added to output coordinates.
+    #[inline]
+    fn origin(&self) -> [usize; 2] {
+        [0, 0]
+    }
+
+    /// Same pixels packed into words, labelled with [`core::set_packed()`] instead of going
+    /// through [`Self::into_args()`] when there are some.
+    #[inline]
+    fn packed(&self) -> Option<Packed<'_>> {
+        None
+    }
+}
+
+impl<'a> IntoArgs for Packed<'a> {
+    type Pixels = Bits<'a>;
+
+    #[inline]
+    fn into_args(self) -> Args<Self::Pixels> {
+        Args::new(self.width, self.height, self.bits())
+    }
+
+    #[inline]
+    fn packed(&self) -> Option<Packed<'_>> {
+        Some(*self)
+    }
 }
 
-impl<P: IntoIterator<Item = bool>> IntoArgs for Args<P> {
+impl<T, P: IntoIterator<Item = T>> IntoArgs<T> for Args<P> {
     type Pixels = P;
 
     #[inline]
@@ -73,3 +473,306 @@ impl<P: IntoIterator<Item = bool>> IntoArgs for Args<P> {
         self
     }
 }
+
+impl<P> Args<P> {
+    /// Same as [`Roi::new()`], with [`Self::width`] as the row stride.
+    pub const fn roi(self, origin: [usize; 2], size: [usize; 2]) -> Roi<Self> {
+        Roi::new(self, origin, size)
+    }
+}
+
+/// Coordinates of vertices found in a [`Roi`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Space {
+    /// Relative to the top-left corner of the region.
+    #[default]
+    Roi,
+    /// Same as in the whole image.
+    Image,
+}
+
+/// Rectangular region of interest of `args`, read without copying it out.
+#[derive(Copy, Clone, Debug)]
+pub struct Roi<A> {
+    pub args: A,
+    pub origin: [usize; 2],
+    /// Width and height, clamped to the ones of `args`.
+    pub size: [usize; 2],
+    pub space: Space,
+}
+
+impl<A> Roi<A> {
+    pub const fn new(args: A, origin: [usize; 2], size: [usize; 2]) -> Self {
+        Self {
+            args,
+            origin,
+            size,
+            space: Space::Roi,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_space(mut self, space: Space) -> Self {
+        self.space = space;
+        self
+    }
+}
+
+impl<T, A: IntoArgs<T>> IntoArgs<T> for Roi<A> {
+    type Pixels = Window<<A::Pixels as IntoIterator>::IntoIter>;
+
+    fn into_args(self) -> Args<Self::Pixels> {
+        let Args {
+            width: stride,
+            height,
+            pixels,
+        } = self.args.into_args();
+        let [x, y] = self.origin;
+        let width = self.size[0].min(stride.saturating_sub(x));
+        let height = self.size[1].min(height.saturating_sub(y));
+        let pixels = Window {
+            pixels: pixels.into_iter(),
+            num_skipped: y * stride + x,
+            num_row_skipped: stride - width,
+            width,
+            column_index: 0,
+            len: width * height,
+        };
+        Args::new(width, height, pixels)
+    }
+
+    fn origin(&self) -> [usize; 2] {
+        match self.space {
+            Space::Roi => [0, 0],
+            Space::Image => {
+                let [x, y] = self.args.origin();
+                [x + self.origin[0], y + self.origin[1]]
+            }
+        }
+    }
+}
+
+/// Pixels of a [`Roi`], skipping the ones outside of it.
+#[derive(Clone, Debug)]
+pub struct Window<I> {
+    pixels: I,
+    num_skipped: usize,
+    num_row_skipped: usize,
+    width: usize,
+    column_index: usize,
+    len: usize,
+}
+
+impl<I: Iterator> Iterator for Window<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        if self.column_index == self.width {
+            self.column_index = 0;
+            self.num_skipped = self.num_row_skipped;
+        }
+        let pixel = match mem::take(&mut self.num_skipped) {
+            0 => self.pixels.next(),
+            num_skipped => self.pixels.nth(num_skipped),
+        };
+        self.column_index += 1;
+        self.len -= 1;
+        if pixel.is_none() {
+            self.len = 0;
+        }
+        pixel
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.len))
+    }
+}
+
+impl<I: Iterator> FusedIterator for Window<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::alloc::vec;
+
+    #[test]
+    fn try_search_limits_cells_before_allocating() {
+        let mut algorithm = Algorithm::<u32>::default().with_max_cells(1 << 20);
+        let pixels = iter::from_fn(|| Some(true));
+        let result = algorithm.try_search(Args::new(65535, 65535, pixels));
+        assert!(matches!(result, Err(Error::CellsTooMany { .. })));
+        assert_eq!(algorithm.cells.capacity(), 0);
+        let (vertices, _) = algorithm.try_search(Args::new(2, 2, [true; 4])).unwrap();
+        assert_eq!(vertices.count(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "16900 vertices are more than indexable 16383")]
+    fn shapes_panics_past_max_index() {
+        let pixels = (0..130 * 130).map(|index| index % 2 == 0 && index / 130 % 2 == 0);
+        Algorithm::new().shapes(Args::new(130, 130, pixels), Winding::CounterClockwise);
+    }
+
+    fn try_shapes(args: impl IntoArgs) -> Result<Shapes, Error> {
+        Algorithm::new().try_shapes(args, Winding::CounterClockwise)
+    }
+
+    /// `len` filled pixels without a size hint.
+    fn unhinted(len: usize) -> impl Iterator<Item = bool> {
+        let mut count = 0;
+        iter::from_fn(move || {
+            count += 1;
+            (count <= len).then_some(true)
+        })
+    }
+
+    #[test]
+    fn try_shapes_reports_pixel_counts() {
+        let too_few = Error::PixelsTooFew {
+            len: 8,
+            expected: 9,
+        };
+        assert_eq!(try_shapes(Args::new(3, 3, [true; 8])), Err(too_few));
+        assert_eq!(try_shapes(Args::new(3, 3, unhinted(8))), Err(too_few));
+        let too_many = Error::PixelsTooMany { expected: 9 };
+        assert_eq!(try_shapes(Args::new(3, 3, [true; 10])), Err(too_many));
+        assert_eq!(try_shapes(Args::new(3, 3, unhinted(10))), Err(too_many));
+        assert_eq!(
+            try_shapes(Args::new(3, 3, iter::repeat(true))),
+            Err(too_many)
+        );
+        assert!(try_shapes(Args::new(3, 3, unhinted(9))).is_ok());
+    }
+
+    #[test]
+    fn try_shapes_reports_dimensions_overflow() {
+        let overflow = Error::DimensionsOverflow {
+            width: 65536,
+            height: 1,
+        };
+        let pixels = |len| iter::repeat_n(false, len);
+        assert_eq!(
+            try_shapes(Args::new(65536, 1, pixels(65536))),
+            Err(overflow)
+        );
+        assert!(Algorithm::<u32>::default()
+            .try_shapes(
+                Args::new(65536, 1, pixels(65536)),
+                Winding::CounterClockwise
+            )
+            .is_ok());
+        // Coordinates offset into the image overflow too, those of the region alone do not.
+        let roi = Args::new(65540, 1, pixels(65540)).roi([10, 0], [65530, 1]);
+        assert!(try_shapes(roi.clone()).is_ok());
+        let overflow = Error::DimensionsOverflow {
+            width: 65530,
+            height: 1,
+        };
+        assert_eq!(try_shapes(roi.with_space(Space::Image)), Err(overflow));
+    }
+
+    #[test]
+    fn try_shapes_reports_vertex_overflow() {
+        let pixels = (0..130 * 130).map(|index| index % 2 == 0 && index / 130 % 2 == 0);
+        let overflow = Error::VertexOverflow {
+            len: 16900,
+            max: 16383,
+        };
+        assert_eq!(
+            try_shapes(Args::new(130, 130, pixels.clone())),
+            Err(overflow)
+        );
+        let shapes = Algorithm::<u32>::default()
+            .try_shapes(Args::new(130, 130, pixels), Winding::CounterClockwise)
+            .unwrap();
+        assert_eq!(shapes.vertices.len(), 16900);
+    }
+
+    #[test]
+    fn try_set_reports_too_few_cells() {
+        let mut cells = vec![Cell::<u16>::EMPTY; core::capacity(3, 3) - 1];
+        let result = core::try_set(&mut cells, 3, 3, [true; 9], Connectivity::Four);
+        let too_few = Error::CellsTooFew {
+            len: cells.len(),
+            capacity: core::capacity(3, 3),
+        };
+        assert_eq!(result, Err(too_few));
+    }
+
+    /// Pixels of `size` at `origin` copied out of a `width` wide image, clamped to it.
+    fn copy(
+        width: usize,
+        pixels: &[bool],
+        origin: [usize; 2],
+        size: [usize; 2],
+    ) -> Args<Vec<bool>> {
+        let height = pixels.len() / width;
+        let [x, y] = origin;
+        let size = [
+            size[0].min(width.saturating_sub(x)),
+            size[1].min(height.saturating_sub(y)),
+        ];
+        let copied = (y..y + size[1])
+            .flat_map(|row| pixels[row * width + x..][..size[0]].iter().copied())
+            .collect();
+        Args::new(size[0], size[1], copied)
+    }
+
+    #[test]
+    fn roi_matches_copied_pixels() {
+        let (width, height) = (37, 29);
+        let pixels = crate::core::tests::pixels(9, width * height);
+        let args = Args::new(width, height, pixels.iter().copied());
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            for (origin, size) in [
+                ([0, 0], [width, height]),
+                ([4, 7], [20, 11]),
+                ([30, 20], [20, 20]),
+                ([36, 0], [1, 29]),
+                ([40, 3], [5, 5]),
+            ] {
+                let offset = origin.map(|value| u16::try_from(value).unwrap());
+                let offset = |[x, y]: [u16; 2]| [x + offset[0], y + offset[1]];
+                let copied = copy(width, &pixels, origin, size);
+                let mut expected = algorithm.shapes(copied, Winding::CounterClockwise);
+                let roi = args.clone().roi(origin, size);
+                let shapes = algorithm.shapes(roi.clone(), Winding::CounterClockwise);
+                assert_eq!(shapes, expected, "{origin:?} {size:?} {connectivity:?}");
+                for vertex in &mut expected.vertices {
+                    *vertex = offset(*vertex);
+                }
+                let roi = roi.with_space(Space::Image);
+                let shapes = algorithm.shapes(roi.clone(), Winding::CounterClockwise);
+                assert_eq!(shapes, expected, "{origin:?} {size:?} {connectivity:?}");
+
+                let (vertices, indices) = algorithm.search(copy(width, &pixels, origin, size));
+                let expected: (Vec<_>, Vec<_>) =
+                    (vertices.map(offset).collect(), indices.collect());
+                let (vertices, indices) = algorithm.search(roi);
+                assert_eq!((vertices.collect(), indices.collect()), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn nested_roi_offsets_by_both_origins() {
+        let (width, height) = (37, 29);
+        let pixels = crate::core::tests::pixels(10, width * height);
+        let args = Args::new(width, height, pixels.iter().copied());
+        let inner = args.roi([3, 2], [30, 25]).with_space(Space::Image);
+        let roi = Roi::new(inner, [5, 4], [12, 9]);
+        let mut algorithm = Algorithm::new();
+        let mut expected =
+            algorithm.shapes(copy(width, &pixels, [8, 6], [12, 9]), Winding::Clockwise);
+        assert_eq!(algorithm.shapes(roi.clone(), Winding::Clockwise), expected);
+        for vertex in &mut expected.vertices {
+            *vertex = [vertex[0] + 8, vertex[1] + 6];
+        }
+        let roi = roi.with_space(Space::Image);
+        assert_eq!(algorithm.shapes(roi, Winding::Clockwise), expected);
+    }
+}
@@ -0,0 +1,611 @@
+use crate::{
+    algorithm::{Args, IntoArgs},
+    core::{Bits, Connectivity, Packed},
+};
+use ::alloc::{vec, vec::Vec};
+use ::core::{
+    iter::FusedIterator,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not},
+};
+
+const BITS: usize = u64::BITS as usize;
+
+/// Smallest integer at or above `value`, which has to fit in [`isize`].
+fn ceil(value: f32) -> isize {
+    let truncated = value as isize;
+    if coordinate(truncated) < value {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+/// Pixel coordinate as shapes are drawn, exact up to `2^24` like the shapes themselves.
+#[allow(clippy::cast_precision_loss)]
+const fn coordinate(value: isize) -> f32 {
+    value as f32
+}
+
+/// Pixels stored as bits, 64 to a word, that can be edited in place and passed to
+/// [`crate::Algorithm`] as they are.
+///
+/// Shapes are drawn in pixel units with `[0.0, 0.0]` at the top-left corner of the first pixel,
+/// covering the pixels whose centers they contain.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    /// Rows of [`Packed::new()`], with bits past the width cleared.
+    pub(crate) words: Vec<u64>,
+}
+
+impl BitGrid {
+    /// Empty grid.
+    #[must_use]
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            words: vec![0; width.div_ceil(BITS) * height],
+        }
+    }
+
+    /// Grid of no pixels, without allocating.
+    pub(crate) const fn empty() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            words: Vec::new(),
+        }
+    }
+
+    /// Copies the pixels of `args`, leaving missing ones empty.
+    #[must_use]
+    pub fn from_args(args: impl IntoArgs) -> Self {
+        let Args {
+            width,
+            height,
+            pixels,
+        } = args.into_args();
+        let mut grid = Self::new(width, height);
+        for (index, pixel) in pixels.into_iter().take(width * height).enumerate() {
+            if pixel {
+                grid.set([index % width, index / width], true);
+            }
+        }
+        grid
+    }
+
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    #[must_use]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    pub(crate) const fn stride(&self) -> usize {
+        self.width.div_ceil(BITS)
+    }
+
+    #[must_use]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    #[must_use]
+    pub fn packed(&self) -> Packed<'_> {
+        Packed::new(self.width, self.height, &self.words)
+    }
+
+    /// Number of filled pixels.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// `false` outside the grid.
+    #[must_use]
+    pub fn get(&self, [x, y]: [usize; 2]) -> bool {
+        x < self.width
+            && y < self.height
+            && self.words[y * self.stride() + x / BITS] >> (x % BITS) & 1 == 1
+    }
+
+    /// Does nothing outside the grid.
+    pub fn set(&mut self, [x, y]: [usize; 2], value: bool) {
+        if x < self.width && y < self.height {
+            let (index, bit) = (y * self.stride() + x / BITS, 1 << (x % BITS));
+            if value {
+                self.words[index] |= bit;
+            } else {
+                self.words[index] &= !bit;
+            }
+        }
+    }
+
+    pub fn fill(&mut self, value: bool) {
+        let [width, height] = [self.width, self.height];
+        self.fill_rect([0, 0], [width, height], value);
+    }
+
+    /// Sets pixels `start..end` of row `y`, which have to be inside the grid.
+    fn fill_span(&mut self, y: usize, start: usize, end: usize, value: bool) {
+        let row = y * self.stride();
+        let mut x = start;
+        while x < end {
+            let (index, bit) = (row + x / BITS, x % BITS);
+            let len = (BITS - bit).min(end - x);
+            let mask = (u64::MAX >> (BITS - len)) << bit;
+            if value {
+                self.words[index] |= mask;
+            } else {
+                self.words[index] &= !mask;
+            }
+            x += len;
+        }
+    }
+
+    /// Fills the rows with centers between `top` and `bottom`, each from the range `row_range`
+    /// gives for the center of its pixels, clipped to the grid.
+    fn fill_rows(
+        &mut self,
+        [top, bottom]: [f32; 2],
+        value: bool,
+        mut row_range: impl FnMut(f32) -> Option<[f32; 2]>,
+    ) {
+        let top = ceil(top - 0.5).max(0);
+        let bottom = (ceil(bottom - 0.5) + 1).min(self.height.cast_signed());
+        for y in top..bottom {
+            let Some([left, right]) = row_range(coordinate(y) + 0.5) else {
+                continue;
+            };
+            let start = ceil(left - 0.5).max(0);
+            let end = ceil(right - 0.5).min(self.width.cast_signed());
+            if start < end {
+                self.fill_span(
+                    y.cast_unsigned(),
+                    start.cast_unsigned(),
+                    end.cast_unsigned(),
+                    value,
+                );
+            }
+        }
+    }
+
+    /// Clipped to the grid.
+    pub fn fill_rect(&mut self, origin: [usize; 2], size: [usize; 2], value: bool) {
+        let end_x = origin[0].saturating_add(size[0]).min(self.width);
+        let end_y = origin[1].saturating_add(size[1]).min(self.height);
+        for y in origin[1]..end_y {
+            self.fill_span(y, origin[0].min(end_x), end_x, value);
+        }
+    }
+
+    pub fn fill_circle(&mut self, center: [f32; 2], radius: f32, value: bool) {
+        let [center_x, center_y] = center;
+        let top_bottom = [center_y - radius, center_y + radius];
+        let width = self.width;
+        self.fill_rows(top_bottom, value, |y| {
+            let dy = y - center_y;
+            // Pixels of the row inside the circle, found by walking in from the sides.
+            let is_inside = |x: isize| {
+                let dx = coordinate(x) + 0.5 - center_x;
+                dx * dx + dy * dy <= radius * radius
+            };
+            let (mut left, mut right) = (
+                ceil(center_x - radius - 0.5).max(0),
+                ceil(center_x + radius + 0.5).min(width.cast_signed()) - 1,
+            );
+            while left <= right && !is_inside(left) {
+                left += 1;
+            }
+            while right >= left && !is_inside(right) {
+                right -= 1;
+            }
+            (left <= right).then_some([coordinate(left), coordinate(right) + 1.0])
+        });
+    }
+
+    /// Line of `thickness` with round ends.
+    pub fn draw_line(&mut self, from: [f32; 2], to: [f32; 2], thickness: f32, value: bool) {
+        let radius = thickness / 2.0;
+        let ([from_x, from_y], [to_x, to_y]) = (from, to);
+        let (dx, dy) = (to_x - from_x, to_y - from_y);
+        let len_squared = dx * dx + dy * dy;
+        let is_inside = |x: f32, y: f32| {
+            let t = if len_squared > 0.0 {
+                (((x - from_x) * dx + (y - from_y) * dy) / len_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let (offset_x, offset_y) = (from_x + t * dx - x, from_y + t * dy - y);
+            offset_x * offset_x + offset_y * offset_y <= radius * radius
+        };
+        let top_bottom = [from_y.min(to_y) - radius, from_y.max(to_y) + radius];
+        let width = self.width;
+        self.fill_rows(top_bottom, value, |y| {
+            // Part of the segment within `radius` of the row bounds its pixels.
+            let (mut low, mut high) = (0.0_f32, 1.0_f32);
+            if dy != 0.0 {
+                let (a, b) = ((y - radius - from_y) / dy, (y + radius - from_y) / dy);
+                (low, high) = (low.max(a.min(b)), high.min(a.max(b)));
+            }
+            if low > high {
+                return None;
+            }
+            let (a, b) = (from_x + low * dx, from_x + high * dx);
+            let (mut left, mut right) = (
+                ceil(a.min(b) - radius - 0.5).max(0),
+                ceil(a.max(b) + radius + 0.5).min(width.cast_signed()) - 1,
+            );
+            while left <= right && !is_inside(coordinate(left) + 0.5, y) {
+                left += 1;
+            }
+            while right >= left && !is_inside(coordinate(right) + 0.5, y) {
+                right -= 1;
+            }
+            (left <= right).then_some([coordinate(left), coordinate(right) + 1.0])
+        });
+    }
+
+    /// Fills the inside of a closed polygon by the even-odd rule.
+    pub fn fill_polygon(&mut self, points: &[[f32; 2]], value: bool) {
+        let Some(top) = points.iter().map(|&[_, y]| y).reduce(f32::min) else {
+            return;
+        };
+        let bottom = points.iter().map(|&[_, y]| y).fold(top, f32::max);
+        let (top, bottom) = (
+            ceil(top - 0.5).max(0),
+            ceil(bottom - 0.5).min(self.height.cast_signed()),
+        );
+        let mut crossings = Vec::new();
+        for y in top..bottom {
+            let center_y = coordinate(y) + 0.5;
+            crossings.clear();
+            for (index, &[from_x, from_y]) in points.iter().enumerate() {
+                let [to_x, to_y] = points[(index + 1) % points.len()];
+                if (from_y <= center_y) != (to_y <= center_y) {
+                    let t = (center_y - from_y) / (to_y - from_y);
+                    crossings.push(from_x + t * (to_x - from_x));
+                }
+            }
+            crossings.sort_unstable_by(f32::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                let start = ceil(pair[0] - 0.5).max(0);
+                let end = ceil(pair[1] - 0.5).min(self.width.cast_signed());
+                if start < end {
+                    self.fill_span(
+                        y.cast_unsigned(),
+                        start.cast_unsigned(),
+                        end.cast_unsigned(),
+                        value,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Sets the pixels connected to `seed` that have the same value as it, and returns how many
+    /// there were. [`Connectivity::Average`] is the same as [`Connectivity::Four`] here.
+    pub fn flood_fill(
+        &mut self,
+        seed: [usize; 2],
+        value: bool,
+        connectivity: Connectivity,
+    ) -> usize {
+        if seed[0] >= self.width || seed[1] >= self.height || self.get(seed) == value {
+            return 0;
+        }
+        let reach = usize::from(connectivity == Connectivity::Eight);
+        let (mut num_filled, mut seeds) = (0, Vec::from([seed]));
+        while let Some([x, y]) = seeds.pop() {
+            if self.get([x, y]) == value {
+                continue;
+            }
+            let (mut start, mut end) = (x, x + 1);
+            while start > 0 && self.get([start - 1, y]) != value {
+                start -= 1;
+            }
+            while end < self.width && self.get([end, y]) != value {
+                end += 1;
+            }
+            self.fill_span(y, start, end, value);
+            num_filled += end - start;
+            let neighbours = [y.checked_sub(1), Some(y + 1).filter(|&y| y < self.height)];
+            for neighbour in neighbours.into_iter().flatten() {
+                let mut is_run = false;
+                for x in start.saturating_sub(reach)..(end + reach).min(self.width) {
+                    let is_target = self.get([x, neighbour]) != value;
+                    if is_target && !is_run {
+                        seeds.push([x, neighbour]);
+                    }
+                    is_run = is_target;
+                }
+            }
+        }
+        num_filled
+    }
+
+    /// Applies `op` to every pair of words, keeping bits past the width cleared.
+    fn zip_with(&mut self, other: &Self, op: impl Fn(u64, u64) -> u64) {
+        assert_eq!(
+            [self.width, self.height],
+            [other.width, other.height],
+            "grids of different sizes"
+        );
+        for (word, &other) in self.words.iter_mut().zip(&other.words) {
+            *word = op(*word, other);
+        }
+    }
+}
+
+impl BitAndAssign<&BitGrid> for BitGrid {
+    /// # Panics
+    /// If the grids are of different sizes.
+    fn bitand_assign(&mut self, rhs: &BitGrid) {
+        self.zip_with(rhs, |a, b| a & b);
+    }
+}
+
+impl BitOrAssign<&BitGrid> for BitGrid {
+    /// # Panics
+    /// If the grids are of different sizes.
+    fn bitor_assign(&mut self, rhs: &BitGrid) {
+        self.zip_with(rhs, |a, b| a | b);
+    }
+}
+
+impl BitXorAssign<&BitGrid> for BitGrid {
+    /// # Panics
+    /// If the grids are of different sizes.
+    fn bitxor_assign(&mut self, rhs: &BitGrid) {
+        self.zip_with(rhs, |a, b| a ^ b);
+    }
+}
+
+impl BitAnd for &BitGrid {
+    type Output = BitGrid;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        let mut grid = self.clone();
+        grid &= rhs;
+        grid
+    }
+}
+
+impl BitOr for &BitGrid {
+    type Output = BitGrid;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        let mut grid = self.clone();
+        grid |= rhs;
+        grid
+    }
+}
+
+impl BitXor for &BitGrid {
+    type Output = BitGrid;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        let mut grid = self.clone();
+        grid ^= rhs;
+        grid
+    }
+}
+
+impl Not for BitGrid {
+    type Output = Self;
+
+    fn not(mut self) -> Self::Output {
+        let stride = self.stride();
+        let last_mask = match self.width % BITS {
+            0 => u64::MAX,
+            len => (1 << len) - 1,
+        };
+        for row in self.words.chunks_exact_mut(stride.max(1)) {
+            for word in row.iter_mut() {
+                *word = !*word;
+            }
+            if let Some(last) = row.last_mut() {
+                *last &= last_mask;
+            }
+        }
+        self
+    }
+}
+
+impl Not for &BitGrid {
+    type Output = BitGrid;
+
+    fn not(self) -> Self::Output {
+        !self.clone()
+    }
+}
+
+impl<'a> IntoArgs for &'a BitGrid {
+    type Pixels = Bits<'a>;
+
+    #[inline]
+    fn into_args(self) -> Args<Self::Pixels> {
+        Args::new(self.width, self.height, self.packed().bits())
+    }
+
+    #[inline]
+    fn packed(&self) -> Option<Packed<'_>> {
+        Some(BitGrid::packed(self))
+    }
+}
+
+impl IntoIterator for BitGrid {
+    type Item = bool;
+    type IntoIter = GridPixels;
+
+    fn into_iter(self) -> Self::IntoIter {
+        GridPixels {
+            grid: self,
+            index: 0,
+        }
+    }
+}
+
+/// Pixels of an owned [`BitGrid`], one row after another.
+#[derive(Clone, Debug)]
+pub struct GridPixels {
+    grid: BitGrid,
+    index: usize,
+}
+
+impl Iterator for GridPixels {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let width = self.grid.width;
+        if self.index >= width * self.grid.height {
+            return None;
+        }
+        let pixel = self.grid.get([self.index % width, self.index / width]);
+        self.index += 1;
+        Some(pixel)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.grid.width * self.grid.height).saturating_sub(self.index);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for GridPixels {}
+
+impl FusedIterator for GridPixels {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::tests::{components, pixels};
+
+    fn grid(width: usize, height: usize, pixels: &[bool]) -> BitGrid {
+        BitGrid::from_args(Args::new(width, height, pixels.iter().copied()))
+    }
+
+    /// Checks every pixel of `grid` against whether its center is inside.
+    fn assert_centers(grid: &BitGrid, is_inside: impl Fn(f32, f32) -> bool) {
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let [center_x, center_y] =
+                    [x, y].map(|value| coordinate(value.cast_signed()) + 0.5);
+                assert_eq!(grid.get([x, y]), is_inside(center_x, center_y), "{x},{y}");
+            }
+        }
+    }
+
+    #[test]
+    fn flood_fill_matches_breadth_first_search() {
+        for connectivity in [
+            Connectivity::Four,
+            Connectivity::Eight,
+            Connectivity::Average,
+        ] {
+            for (seed, (width, height)) in (1..).zip([(1, 1), (5, 4), (63, 9), (70, 11), (130, 6)])
+            {
+                let pixels = pixels(seed, width * height);
+                for start in (0..pixels.len()).step_by(7) {
+                    let value = !pixels[start];
+                    // Pixels equal to the one at `start`, of which its component gets filled.
+                    let same: Vec<bool> = pixels.iter().map(|&pixel| pixel != value).collect();
+                    let labels = components(width, height, &same, connectivity);
+                    let mut expected = pixels.clone();
+                    for (pixel, label) in expected.iter_mut().zip(&labels) {
+                        if *label == labels[start] {
+                            *pixel = value;
+                        }
+                    }
+                    let num_filled = labels
+                        .iter()
+                        .filter(|&&label| label == labels[start])
+                        .count();
+
+                    let mut grid = grid(width, height, &pixels);
+                    let seed = [start % width, start / width];
+                    assert_eq!(grid.flood_fill(seed, value, connectivity), num_filled);
+                    assert_eq!(grid, self::grid(width, height, &expected), "{seed:?}");
+                    assert_eq!(grid.flood_fill(seed, value, connectivity), 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shapes_cover_pixels_with_centers_inside() {
+        for (width, height) in [(1, 1), (20, 15), (70, 33)] {
+            for (center, radius) in [
+                ([5.25, 4.5], 3.75),
+                ([4.0, 4.5], 3.5),
+                ([0.0, 0.0], 8.0),
+                ([40.5, 16.0], 12.25),
+            ] {
+                let mut grid = BitGrid::new(width, height);
+                grid.fill_circle(center, radius, true);
+                assert_centers(&grid, |x, y| {
+                    let (dx, dy) = (x - center[0], y - center[1]);
+                    dx * dx + dy * dy <= radius * radius
+                });
+            }
+
+            for (from, to, thickness) in [
+                ([2.0, 3.0], [17.5, 9.25], 3.0),
+                ([-4.0, 20.0], [60.25, -2.5], 5.5),
+                ([8.5, 8.5], [8.5, 8.5], 6.0),
+                ([1.0, 12.0], [66.0, 12.0], 1.0),
+            ] {
+                let mut grid = BitGrid::new(width, height);
+                grid.draw_line(from, to, thickness, true);
+                let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+                let len_squared = dx * dx + dy * dy;
+                assert_centers(&grid, |x, y| {
+                    let t = if len_squared > 0.0 {
+                        (((x - from[0]) * dx + (y - from[1]) * dy) / len_squared).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let (offset_x, offset_y) = (from[0] + t * dx - x, from[1] + t * dy - y);
+                    let radius = thickness / 2.0;
+                    offset_x * offset_x + offset_y * offset_y <= radius * radius
+                });
+            }
+
+            for points in [
+                &[[1.0, 1.0], [18.5, 3.25], [9.0, 14.0]][..],
+                // Self-intersecting star, partly outside.
+                &[
+                    [10.0, -3.0],
+                    [16.5, 20.0],
+                    [-2.0, 5.5],
+                    [68.0, 6.0],
+                    [3.25, 18.0],
+                ],
+            ] {
+                let mut grid = BitGrid::new(width, height);
+                grid.fill_polygon(points, true);
+                assert_centers(&grid, |x, y| {
+                    let mut is_inside = false;
+                    for (index, &[from_x, from_y]) in points.iter().enumerate() {
+                        let [to_x, to_y] = points[(index + 1) % points.len()];
+                        if (from_y <= y) != (to_y <= y) {
+                            let t = (y - from_y) / (to_y - from_y);
+                            if from_x + t * (to_x - from_x) <= x {
+                                is_inside = !is_inside;
+                            }
+                        }
+                    }
+                    is_inside
+                });
+            }
+        }
+    }
+}
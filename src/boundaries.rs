@@ -0,0 +1,197 @@
+use crate::{
+    algorithm::{Args, IntoArgs},
+    core::Primitive,
+};
+use ::alloc::{vec, vec::Vec};
+use ::core::mem;
+
+/// Straight boundary between two regions.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Edge<L, P = u16> {
+    /// Indices into [`Boundaries::vertices`], from the smaller coordinate to the larger one.
+    pub indices: [P; 2],
+    /// Labels of the pixels above and below a horizontal edge, or to the left and to the right
+    /// of a vertical one, [`None`] outside of the image.
+    pub labels: [Option<L>; 2],
+}
+
+/// Boundaries of the regions of a label map, every edge found once with the labels on both of
+/// its sides, so neighbouring regions share their vertices.
+///
+/// Edges with a label on either side make up the rings of the regions of that label.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Boundaries<L, P = u16> {
+    pub vertices: Vec<[P; 2]>,
+    pub edges: Vec<Edge<L, P>>,
+}
+
+impl<L: Copy + Eq> Boundaries<L> {
+    #[must_use]
+    pub fn new(args: impl IntoArgs<L>) -> Self {
+        Self::from_args(args)
+    }
+}
+
+impl<L: Copy + Eq, P: Primitive> Boundaries<L, P> {
+    /// Walks the grid points row by row, with vertices where boundaries turn or meet, offset by
+    /// the origin of `args`.
+    ///
+    /// # Panics
+    /// If there are more vertices than `P` can index
+    #[must_use]
+    pub fn from_args(args: impl IntoArgs<L>) -> Self {
+        let [x0, y0] = args.origin();
+        let Args {
+            width,
+            height,
+            pixels,
+        } = args.into_args();
+        let mut pixels = pixels.into_iter();
+        let (mut vertices, mut edges) = (Vec::new(), Vec::new());
+        if width == 0 || height == 0 {
+            return Self { vertices, edges };
+        }
+        let (mut above, mut below) = (vec![None; width], Vec::with_capacity(width));
+        // Vertex and labels of the edge going down from every column.
+        let mut columns = vec![None; width + 1];
+        for y in 0..=height {
+            below.clear();
+            if y < height {
+                below.extend(pixels.by_ref().take(width).map(Some));
+            }
+            below.resize(width, None);
+            let at = |row: &[Option<L>], x: Option<usize>| x.and_then(|x| row.get(x).copied()?);
+            // Vertex and labels of the edge going right along the row.
+            let mut row = None;
+            for (x, column) in columns.iter_mut().enumerate() {
+                let (top_left, top_right) = (at(&above, x.checked_sub(1)), at(&above, Some(x)));
+                let (bottom_left, bottom_right) =
+                    (at(&below, x.checked_sub(1)), at(&below, Some(x)));
+                let (up, down) = (top_left != top_right, bottom_left != bottom_right);
+                let (left, right) = (top_left != bottom_left, top_right != bottom_right);
+                let is_straight =
+                    (up && down && !left && !right) || (left && right && !up && !down);
+                if !(up || down || left || right) || is_straight {
+                    continue;
+                }
+                assert!(vertices.len() <= P::MAX.into_usize(), "too many vertices");
+                let index = P::from_usize(vertices.len());
+                vertices.push([P::from_usize(x0 + x), P::from_usize(y0 + y)]);
+                if let Some((start, labels)) = row.take().filter(|_| left) {
+                    edges.push(Edge {
+                        indices: [start, index],
+                        labels,
+                    });
+                }
+                if let Some((start, labels)) = column.take().filter(|_| up) {
+                    edges.push(Edge {
+                        indices: [start, index],
+                        labels,
+                    });
+                }
+                if right {
+                    row = Some((index, [top_right, bottom_right]));
+                }
+                if down {
+                    *column = Some((index, [bottom_left, bottom_right]));
+                }
+            }
+            mem::swap(&mut above, &mut below);
+        }
+        Self { vertices, edges }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::tests::pixels, Space};
+    use ::alloc::collections::BTreeMap;
+
+    /// Labels from 0 to 3 of `width * height` pixels.
+    fn labels(seed: u64, width: usize, height: usize) -> Vec<u8> {
+        pixels(seed, width * height * 2)
+            .chunks(2)
+            .map(|bits| u8::from(bits[0]) * 2 + u8::from(bits[1]))
+            .collect()
+    }
+
+    /// Labels on both sides of every unit segment, keyed by its smaller end and whether it is
+    /// horizontal.
+    type Segments = BTreeMap<([u16; 2], bool), [Option<u8>; 2]>;
+
+    fn segments(boundaries: &Boundaries<u8>) -> Segments {
+        let mut segments = BTreeMap::new();
+        for edge in &boundaries.edges {
+            let [from, to] = edge
+                .indices
+                .map(|index| boundaries.vertices[usize::from(index)]);
+            assert!(from < to && (from[0] == to[0]) != (from[1] == to[1]));
+            let is_horizontal = from[1] == to[1];
+            for step in 0..(to[0] - from[0]).max(to[1] - from[1]) {
+                let start = if is_horizontal {
+                    [from[0] + step, from[1]]
+                } else {
+                    [from[0], from[1] + step]
+                };
+                let labels = segments.insert((start, is_horizontal), edge.labels);
+                assert_eq!(labels, None, "{start:?} covered twice");
+            }
+        }
+        segments
+    }
+
+    #[test]
+    fn edges_cover_every_boundary_segment_once() {
+        for (seed, (width, height)) in (1..).zip([(1, 1), (3, 2), (9, 7), (20, 13)]) {
+            let labels = labels(seed, width, height);
+            let boundaries = Boundaries::new(Args::new(width, height, labels.iter().copied()));
+            let mut vertices = boundaries.vertices.clone();
+            vertices.sort_unstable();
+            vertices.dedup();
+            assert_eq!(vertices.len(), boundaries.vertices.len());
+
+            let label = |x: usize, y: Option<usize>| {
+                y.filter(|&y| x < width && y < height)
+                    .map(|y| labels[y * width + x])
+            };
+            let mut expected = BTreeMap::new();
+            for y in 0..=height {
+                for x in 0..=width {
+                    let start = [x, y].map(|value| u16::try_from(value).unwrap());
+                    let above_below = [label(x, y.checked_sub(1)), label(x, Some(y))];
+                    if x < width && above_below[0] != above_below[1] {
+                        expected.insert((start, true), above_below);
+                    }
+                    let left_right = [
+                        x.checked_sub(1).and_then(|x| label(x, Some(y))),
+                        label(x, Some(y)),
+                    ];
+                    if y < height && left_right[0] != left_right[1] {
+                        expected.insert((start, false), left_right);
+                    }
+                }
+            }
+            assert_eq!(segments(&boundaries), expected, "{width}x{height}");
+        }
+    }
+
+    #[test]
+    fn roi_matches_copied_labels() {
+        let (width, height) = (20, 13);
+        let labels = labels(5, width, height);
+        let ([x, y], [roi_width, roi_height]) = ([3, 4], [11, 6]);
+        let copied: Vec<u8> = (y..y + roi_height)
+            .flat_map(|row| labels[row * width + x..][..roi_width].iter().copied())
+            .collect();
+        let mut expected = Boundaries::new(Args::new(roi_width, roi_height, copied));
+        let args = Args::new(width, height, labels.iter().copied());
+        let roi = args.roi([x, y], [roi_width, roi_height]);
+        assert_eq!(Boundaries::new(roi.clone()), expected);
+        let offset = [x, y].map(|value| u16::try_from(value).unwrap());
+        for vertex in &mut expected.vertices {
+            *vertex = [vertex[0] + offset[0], vertex[1] + offset[1]];
+        }
+        assert_eq!(Boundaries::new(roi.with_space(Space::Image)), expected);
+    }
+}
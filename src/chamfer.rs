@@ -0,0 +1,217 @@
+use crate::{
+    core::Primitive,
+    shapes::{Ring, Shape, Shapes},
+};
+use ::alloc::vec::Vec;
+use ::core::iter;
+
+fn cross(origin: [isize; 2], a: [isize; 2], b: [isize; 2]) -> isize {
+    (a[0] - origin[0]) * (b[1] - origin[1]) - (a[1] - origin[1]) * (b[0] - origin[0])
+}
+
+/// Cuts every corner of `ring` between the middles of the segments meeting there, and drops
+/// vertices left in the middle of straight runs, exact in [`f32`] for images up to `2^23`
+/// pixels wide.
+#[allow(clippy::cast_precision_loss)]
+fn chamfer_ring<P: Primitive>(
+    vertices: &[[P; 2]],
+    ring: &[P],
+    chamfered_vertices: &mut Vec<[f32; 2]>,
+) -> Ring<P> {
+    // Doubled, so that the middles of unit segments stay integers.
+    let point =
+        |index: P| vertices[index.into_usize()].map(|value| 2 * value.into_usize().cast_signed());
+    let len = ring.len();
+    let mut points: Vec<[isize; 2]> = Vec::with_capacity(2 * len);
+    for (index, &corner) in ring.iter().enumerate() {
+        let (prev, corner, next) = (
+            point(ring[(index + len - 1) % len]),
+            point(corner),
+            point(ring[(index + 1) % len]),
+        );
+        let to_prev = [
+            (prev[0] - corner[0]).signum(),
+            (prev[1] - corner[1]).signum(),
+        ];
+        let to_next = [
+            (next[0] - corner[0]).signum(),
+            (next[1] - corner[1]).signum(),
+        ];
+        for point in [
+            [corner[0] + to_prev[0], corner[1] + to_prev[1]],
+            [corner[0] + to_next[0], corner[1] + to_next[1]],
+        ] {
+            while points.len() >= 2
+                && cross(points[points.len() - 2], points[points.len() - 1], point) == 0
+            {
+                points.pop();
+            }
+            if points.last() != Some(&point) {
+                points.push(point);
+            }
+        }
+    }
+    while points.len() > 3
+        && cross(
+            points[points.len() - 2],
+            points[points.len() - 1],
+            points[0],
+        ) == 0
+    {
+        points.pop();
+    }
+    while points.len() > 3 && cross(points[points.len() - 1], points[0], points[1]) == 0 {
+        points.remove(0);
+    }
+
+    let chamfered_ring = (chamfered_vertices.len()..chamfered_vertices.len() + points.len())
+        .map(P::from_usize)
+        .collect::<Vec<_>>();
+    chamfered_vertices.extend(
+        points
+            .into_iter()
+            .map(|[x, y]| [x as f32 * 0.5, y as f32 * 0.5]),
+    );
+    chamfered_ring.into()
+}
+
+/// Same `shapes`, outlined as marching squares through the centers of pixels would.
+///
+/// # Panics
+/// If there are more chamfered vertices than `P` can index
+pub(crate) fn chamfer<P: Primitive>(shapes: &Shapes<P, P>) -> Shapes<f32, P> {
+    let mut vertices = Vec::new();
+    let mut chamfer = |ring: &Ring<P>| chamfer_ring(&shapes.vertices, ring, &mut vertices);
+    let chamfered = shapes
+        .shapes
+        .iter()
+        .map(|shape| Shape {
+            outer: chamfer(&shape.outer),
+            holes: shape.holes.iter().map(&mut chamfer).collect(),
+        })
+        .collect();
+    assert!(
+        vertices.len().saturating_sub(1) <= P::MAX.into_usize(),
+        "too many chamfered vertices to index"
+    );
+    Shapes {
+        vertices,
+        shapes: chamfered,
+    }
+}
+
+/// Edges of every ring of `shapes`, from each vertex to the next one.
+pub(crate) fn edges<P: Primitive>(shapes: Vec<Shape<P>>) -> impl Iterator<Item = [P; 2]> {
+    shapes
+        .into_iter()
+        .flat_map(|shape| iter::once(shape.outer).chain(shape.holes))
+        .flat_map(|ring| {
+            let len = ring.len();
+            (0..len).map(move |position| [ring[position], ring[(position + 1) % len]])
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{tests::pixels, Connectivity},
+        Algorithm, Args,
+    };
+    use ::alloc::{collections::BTreeSet, vec, vec::Vec};
+
+    /// Undirected steps of half a pixel diagonally or of a whole pixel straight, in doubled
+    /// coordinates.
+    type Pieces = BTreeSet<[[i32; 2]; 2]>;
+
+    fn insert(pieces: &mut Pieces, from: [i32; 2], to: [i32; 2]) {
+        let [dx, dy] = [to[0] - from[0], to[1] - from[1]];
+        assert!(
+            dx == 0 || dy == 0 || dx.abs() == dy.abs(),
+            "{from:?} {to:?}"
+        );
+        let unit = if dx != 0 && dy != 0 { 1 } else { 2 };
+        let step = [dx.signum() * unit, dy.signum() * unit];
+        for offset in 0..dx.abs().max(dy.abs()) / unit {
+            let a = [from[0] + offset * step[0], from[1] + offset * step[1]];
+            let b = [a[0] + step[0], a[1] + step[1]];
+            assert!(pieces.insert([a.min(b), a.max(b)]), "{a:?} {b:?} twice");
+        }
+    }
+
+    /// Segments of classic marching squares through the centers of pixels, joining the filled
+    /// pixels of saddles with [`Connectivity::Eight`].
+    fn marching_squares(
+        width: usize,
+        height: usize,
+        pixels: &[bool],
+        connectivity: Connectivity,
+    ) -> Pieces {
+        let pixel = |x: usize, y: usize| {
+            x.checked_sub(1)
+                .zip(y.checked_sub(1))
+                .is_some_and(|(x, y)| x < width && y < height && pixels[y * width + x])
+        };
+        let mut pieces = Pieces::new();
+        for j in 0..=height {
+            for i in 0..=width {
+                // Shifted by one, so that pixels outside to the top-left are empty too.
+                let [top_left, top_right, bottom_left, bottom_right] =
+                    [(i, j), (i + 1, j), (i, j + 1), (i + 1, j + 1)].map(|(x, y)| pixel(x, y));
+                let [x, y] = [i, j].map(|value| 2 * i32::try_from(value).unwrap());
+                let [top, right, bottom, left] = [[x, y - 1], [x + 1, y], [x, y + 1], [x - 1, y]];
+                let crossings: Vec<[i32; 2]> = [
+                    (top_left != top_right, top),
+                    (top_right != bottom_right, right),
+                    (bottom_left != bottom_right, bottom),
+                    (top_left != bottom_left, left),
+                ]
+                .into_iter()
+                .filter_map(|(is_crossed, middle)| is_crossed.then_some(middle))
+                .collect();
+                match *crossings.as_slice() {
+                    [] => {}
+                    [from, to] => insert(&mut pieces, from, to),
+                    _ => {
+                        // Corners cut around the pixels that are not joined.
+                        let is_joined = connectivity == Connectivity::Eight;
+                        if top_left == is_joined {
+                            insert(&mut pieces, top, right);
+                            insert(&mut pieces, left, bottom);
+                        } else {
+                            insert(&mut pieces, top, left);
+                            insert(&mut pieces, right, bottom);
+                        }
+                    }
+                }
+            }
+        }
+        pieces
+    }
+
+    #[test]
+    fn chamfered_edges_match_marching_squares() {
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            for (seed, (width, height)) in (1..).zip([(1, 1), (2, 2), (5, 3), (16, 11), (30, 23)]) {
+                let pixels = pixels(seed, width * height);
+                let args = Args::new(width, height, pixels.iter().copied());
+                let (vertices, edges) = algorithm.chamfered_search(args);
+                #[allow(clippy::cast_possible_truncation)]
+                let vertices: Vec<[i32; 2]> = vertices
+                    .map(|vertex| vertex.map(|value| (value * 2.0) as i32))
+                    .collect();
+                let mut pieces = Pieces::new();
+                let mut degrees = vec![0; vertices.len()];
+                for [from, to] in edges {
+                    let (from, to) = (usize::from(from), usize::from(to));
+                    degrees[from] += 1;
+                    degrees[to] += 1;
+                    insert(&mut pieces, vertices[from], vertices[to]);
+                }
+                assert!(degrees.iter().all(|&degree| degree == 2));
+                let expected = marching_squares(width, height, &pixels, connectivity);
+                assert_eq!(pieces, expected, "{width}x{height} {connectivity:?}");
+            }
+        }
+    }
+}
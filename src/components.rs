@@ -0,0 +1,148 @@
+use crate::{
+    core::Primitive,
+    shapes::{Ring, Shapes},
+};
+use ::alloc::{vec, vec::Vec};
+use ::core::iter;
+
+/// Separate blob of filled pixels, connected the same way as the [`crate::Algorithm`]
+/// connectivity joins saddles.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Component<P = u16> {
+    pub outer: Ring<P>,
+    pub holes: Vec<Ring<P>>,
+    pub num_pixels: usize,
+    /// Top-left corner of the bounding box, in the same space as the vertices.
+    pub origin: [usize; 2],
+    pub size: [usize; 2],
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Components<P = u16> {
+    pub width: usize,
+    pub height: usize,
+    /// Index of the component plus one for every filled pixel and zero for empty ones, row by
+    /// row.
+    pub labels: Vec<P>,
+    pub vertices: Vec<[P; 2]>,
+    pub components: Vec<Component<P>>,
+}
+
+impl<P: Primitive> Components<P> {
+    /// Fills the labels of every component from its rings, row by row between the crossings
+    /// of vertical edges.
+    pub(crate) fn new(
+        shapes: Shapes<P, P>,
+        [width, height]: [usize; 2],
+        [origin_x, origin_y]: [usize; 2],
+    ) -> Self {
+        let Shapes { vertices, shapes } = shapes;
+        let vertex = |index: P| vertices[index.into_usize()].map(Primitive::into_usize);
+        let mut labels = vec![P::from(0); width * height];
+        let mut crossings = Vec::new();
+        let components = shapes
+            .into_iter()
+            .enumerate()
+            .map(|(index, shape)| {
+                let label = P::from_usize(index + 1);
+                crossings.clear();
+                for ring in iter::once(&shape.outer).chain(&shape.holes) {
+                    for (&from, &to) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                        let ([x, from_y], [_, to_y]) = (vertex(from), vertex(to));
+                        let rows = from_y.min(to_y) - origin_y..from_y.max(to_y) - origin_y;
+                        crossings.extend(rows.map(|y| (y, x - origin_x)));
+                    }
+                }
+                crossings.sort_unstable();
+                let mut num_pixels = 0;
+                for pair in crossings.chunks_exact(2) {
+                    let [(y, left), (_, right)] = [pair[0], pair[1]];
+                    labels[y * width + left..y * width + right].fill(label);
+                    num_pixels += right - left;
+                }
+                let (min, max) = shape.outer.iter().map(|&index| vertex(index)).fold(
+                    ([usize::MAX; 2], [0; 2]),
+                    |(min, max), [x, y]| {
+                        (
+                            [min[0].min(x), min[1].min(y)],
+                            [max[0].max(x), max[1].max(y)],
+                        )
+                    },
+                );
+                Component {
+                    outer: shape.outer,
+                    holes: shape.holes,
+                    num_pixels,
+                    origin: min,
+                    size: [max[0] - min[0], max[1] - min[1]],
+                }
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            labels,
+            vertices,
+            components,
+        }
+    }
+
+    /// Index of the component the pixel at `[x, y]` belongs to.
+    #[must_use]
+    pub fn get(&self, [x, y]: [usize; 2]) -> Option<usize> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.labels[y * self.width + x].into_usize().checked_sub(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{
+            tests::{components, pixels},
+            Connectivity,
+        },
+        Algorithm, Args, Winding,
+    };
+
+    #[test]
+    fn labels_match_breadth_first_search() {
+        for (seed, (width, height)) in (1..).zip([(1, 1), (4, 3), (13, 9), (31, 22)]) {
+            let pixels = pixels(seed, width * height);
+            for connectivity in [Connectivity::Four, Connectivity::Eight] {
+                let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+                let found = algorithm.components(
+                    Args::new(width, height, pixels.iter().copied()),
+                    Winding::CounterClockwise,
+                );
+                let labels = components(width, height, &pixels, connectivity);
+                for (index, &label) in labels.iter().enumerate() {
+                    let (x, y) = (index % width, index / width);
+                    assert_eq!(found.get([x, y]), label, "{x},{y} {connectivity:?}");
+                }
+                assert_eq!(found.get([width, 0]), None);
+                let num_components = labels.iter().flatten().max().map_or(0, |max| max + 1);
+                assert_eq!(found.components.len(), num_components);
+                for (label, component) in found.components.iter().enumerate() {
+                    let positions = (0..width * height)
+                        .filter(|&index| labels[index] == Some(label))
+                        .map(|index| [index % width, index / width]);
+                    let (min, max) =
+                        positions
+                            .clone()
+                            .fold(([usize::MAX; 2], [0; 2]), |(min, max), [x, y]| {
+                                (
+                                    [min[0].min(x), min[1].min(y)],
+                                    [max[0].max(x), max[1].max(y)],
+                                )
+                            });
+                    assert_eq!(component.num_pixels, positions.count());
+                    assert_eq!(component.origin, min);
+                    assert_eq!(component.size, [max[0] + 1 - min[0], max[1] + 1 - min[1]]);
+                }
+            }
+        }
+    }
+}
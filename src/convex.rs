@@ -0,0 +1,205 @@
+use crate::{
+    core::Primitive,
+    shapes::{Ring, Shape, Shapes},
+    triangulate::triangulate,
+};
+use ::alloc::{collections::BTreeMap, vec, vec::Vec};
+use ::core::{mem, slice};
+
+// Vertices of pixel shapes have the same type as indices.
+#[allow(clippy::mismatching_type_param_order)]
+impl<P: Primitive> Shapes<P, P> {
+    /// Splits every shape into convex pieces wound the same way as its outer ring, with at most
+    /// `max_vertices` corners each, or at least three, with coordinates above `2^53` rounded.
+    ///
+    /// # Panics
+    /// If there are more vertices than [`u32`] can index
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn convex_decomposition(&self, max_vertices: Option<usize>) -> Vec<Ring<P>> {
+        convex_decomposition(&self.shapes, max_vertices, |index| {
+            self.vertices[index].map(|coordinate| coordinate.into_usize() as f64)
+        })
+    }
+}
+
+impl<P: Primitive> Shapes<f32, P> {
+    /// Same as [`Shapes::convex_decomposition()`] of pixel shapes.
+    ///
+    /// # Panics
+    /// If there are more vertices than [`u32`] can index
+    #[must_use]
+    pub fn convex_decomposition(&self, max_vertices: Option<usize>) -> Vec<Ring<P>> {
+        convex_decomposition(&self.shapes, max_vertices, |index| {
+            self.vertices[index].map(f64::from)
+        })
+    }
+}
+
+/// Joins the triangles of every shape across their shared edges while both ends of the edge
+/// stay convex, following Hertel and Mehlhorn, which gives at most four times as many pieces as
+/// the fewest possible.
+fn convex_decomposition<P: Primitive>(
+    shapes: &[Shape<P>],
+    max_vertices: Option<usize>,
+    vertex: impl Fn(usize) -> [f64; 2],
+) -> Vec<Ring<P>> {
+    let max_vertices = max_vertices.map_or(usize::MAX, |max_vertices| max_vertices.max(3));
+    let cross = |a: u32, b: u32, c: u32| {
+        let ([ax, ay], [bx, by], [cx, cy]) =
+            (vertex(a as usize), vertex(b as usize), vertex(c as usize));
+        (bx - ax) * (cy - by) - (by - ay) * (cx - bx)
+    };
+    let mut pieces = Vec::new();
+    for shape in shapes {
+        let triangles = triangulate(slice::from_ref(shape), &vertex);
+        let Some(&[first_a, first_b, first_c]) = triangles.first_chunk() else {
+            continue;
+        };
+        // Turn of every corner of a convex piece, or zero along its straight sides.
+        let sign = cross(first_a, first_b, first_c).signum();
+        let turn = |a: u32, b: u32, c: u32| cross(a, b, c) * sign;
+        let mut rings: Vec<Vec<u32>> = triangles.chunks_exact(3).map(<[u32]>::to_vec).collect();
+        let mut corners = vec![3; rings.len()];
+        // Piece on the left of every directed edge, `None` for edges of more than one triangle.
+        let mut edges = BTreeMap::new();
+        for (piece, ring) in rings.iter().enumerate() {
+            for position in 0..3 {
+                edges
+                    .entry([ring[position], ring[(position + 1) % 3]])
+                    .and_modify(|owner| *owner = None)
+                    .or_insert(Some(piece));
+            }
+        }
+        let diagonals: Vec<[u32; 2]> = edges
+            .iter()
+            .filter(|&(&[a, b], owner)| {
+                a < b && owner.is_some() && matches!(edges.get(&[b, a]), Some(Some(_)))
+            })
+            .map(|(&edge, _)| edge)
+            .collect();
+        for [a, b] in diagonals {
+            // `p` goes from `a` to `b` and `q` back.
+            let (Some(&Some(p)), Some(&Some(q))) = (edges.get(&[a, b]), edges.get(&[b, a])) else {
+                continue;
+            };
+            if p == q {
+                continue;
+            }
+            let find = |ring: &[u32], from: u32, to: u32| {
+                let len = ring.len();
+                (0..len)
+                    .find(|&position| ring[position] == from && ring[(position + 1) % len] == to)
+            };
+            let (Some(i), Some(j)) = (find(&rings[p], a, b), find(&rings[q], b, a)) else {
+                continue;
+            };
+            let at = |ring: &[u32], position: usize| ring[position % ring.len()];
+            let (p_len, q_len) = (rings[p].len(), rings[q].len());
+            let (prev_a, next_b) = (at(&rings[p], i + p_len - 1), at(&rings[p], i + 2));
+            let (prev_b, next_a) = (at(&rings[q], j + q_len - 1), at(&rings[q], j + 2));
+            let (turn_a, turn_b) = (turn(prev_a, a, next_a), turn(prev_b, b, next_b));
+            if turn_a < 0.0 || turn_b < 0.0 {
+                continue;
+            }
+            let was_corner = [
+                turn(prev_a, a, b),
+                turn(a, b, next_b),
+                turn(prev_b, b, a),
+                turn(b, a, next_a),
+            ]
+            .into_iter()
+            .filter(|&turn| turn != 0.0)
+            .count();
+            let is_corner = [turn_a, turn_b]
+                .into_iter()
+                .filter(|&turn| turn != 0.0)
+                .count();
+            let merged = corners[p] + corners[q] + is_corner - was_corner;
+            if merged > max_vertices {
+                continue;
+            }
+            // The vertices of the smaller piece go between the ends of the edge in the larger.
+            let (into, from, position, start) = if p_len >= q_len {
+                (p, q, i + 1, j + 2)
+            } else {
+                (q, p, j + 1, i + 2)
+            };
+            let from_ring = mem::take(&mut rings[from]);
+            let from_len = from_ring.len();
+            let inserted = (0..from_len - 2).map(|offset| from_ring[(start + offset) % from_len]);
+            rings[into].splice(position..position, inserted);
+            for position in 0..from_len {
+                let edge = [from_ring[position], from_ring[(position + 1) % from_len]];
+                if let Some(Some(owner)) = edges.get_mut(&edge) {
+                    *owner = into;
+                }
+            }
+            edges.remove(&[a, b]);
+            edges.remove(&[b, a]);
+            corners[into] = merged;
+        }
+        pieces.extend(
+            rings
+                .into_iter()
+                .filter(|ring| !ring.is_empty())
+                .map(|ring| corner_ring(&ring, turn)),
+        );
+    }
+    pieces
+}
+
+/// Vertices of `ring` that are not along a straight side.
+fn corner_ring<P: Primitive>(ring: &[u32], turn: impl Fn(u32, u32, u32) -> f64) -> Ring<P> {
+    let len = ring.len();
+    let corners = (0..len)
+        .filter(|&position| {
+            let (prev, next) = (ring[(position + len - 1) % len], ring[(position + 1) % len]);
+            turn(prev, ring[position], next) != 0.0
+        })
+        .map(|position| P::from_usize(ring[position] as usize));
+    Ring(corners.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{tests::pixels, Connectivity},
+        Algorithm, Args, Winding,
+    };
+
+    #[test]
+    fn pieces_are_convex_within_max_vertices_and_cover_pixels() {
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            for (seed, (width, height)) in (1..).zip([(1, 1), (3, 2), (8, 8), (17, 5), (24, 24)]) {
+                let pixels = pixels(seed, width * height);
+                let shapes = algorithm.shapes(
+                    Args::new(width, height, pixels.iter().copied()),
+                    Winding::CounterClockwise,
+                );
+                let count: i64 = pixels.iter().map(|&pixel| i64::from(pixel)).sum();
+                for max_vertices in [None, Some(3), Some(4), Some(6)] {
+                    let mut twice_area = 0;
+                    for piece in shapes.convex_decomposition(max_vertices) {
+                        assert!(piece.len() >= 3);
+                        assert!(piece.len() <= max_vertices.unwrap_or(usize::MAX));
+                        let len = piece.len();
+                        for position in 0..len {
+                            let [[ax, ay], [bx, by], [cx, cy]] = [0, 1, 2].map(|offset| {
+                                let index = piece[(position + offset) % len];
+                                shapes.vertices[usize::from(index)].map(i64::from)
+                            });
+                            // Outer rings wound counter-clockwise with `y` up turn right on
+                            // screen.
+                            let turn = (bx - ax) * (cy - by) - (by - ay) * (cx - bx);
+                            assert!(turn > 0, "{piece:?} {connectivity:?} {max_vertices:?}");
+                            twice_area += ax * by - bx * ay;
+                        }
+                    }
+                    assert_eq!(twice_area, 2 * count, "{width}x{height} {connectivity:?}");
+                }
+            }
+        }
+    }
+}
@@ -1,18 +1,85 @@
-use ::core::{iter::FusedIterator, mem};
+use ::core::{
+    fmt::{self, Debug},
+    hash::Hash,
+    iter::FusedIterator,
+    ops::{BitAnd, BitOr, BitOrAssign, BitXor, Not, Shl, Shr, Sub},
+};
 
-type Primitive = u16;
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Unsigned integer cells, vertex coordinates and vertex indices are stored in.
+///
+/// Two bits of a cell are taken by flags, so [`u16`] cells index up to 16383 vertices and
+/// coordinates of images up to 65535 pixels wide.
+pub trait Primitive:
+    sealed::Sealed
+    + Copy
+    + Ord
+    + Hash
+    + Debug
+    + Default
+    + Send
+    + Sync
+    + From<u8>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitOrAssign
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+    + Shl<u32, Output = Self>
+    + Shr<u32, Output = Self>
+    + Sub<Output = Self>
+{
+    const MAX: Self;
+
+    /// Truncates `value` if it does not fit.
+    fn from_usize(value: usize) -> Self;
+
+    fn into_usize(self) -> usize;
+}
 
-const EMPTY: Primitive = 0;
+macro_rules! impl_primitive {
+    ($($primitive:ty),*) => {$(
+        impl sealed::Sealed for $primitive {}
 
+        impl Primitive for $primitive {
+            const MAX: Self = <$primitive>::MAX;
+
+            #[inline]
+            fn from_usize(value: usize) -> Self {
+                value as _
+            }
+
+            #[inline]
+            fn into_usize(self) -> usize {
+                self as _
+            }
+        }
+    )*};
+}
+
+impl_primitive!(u16, u32, u64);
+
+/// Vertex index and flags of a grid point, the index starting at the third bit, so
+/// `Cell<u16>` indexes up to 16383 vertices.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 #[repr(transparent)]
-pub struct Cell(Primitive);
+pub struct Cell<P = u16>(P);
 
 impl Cell {
-    pub const EMPTY: Self = Self(EMPTY);
+    pub const EMPTY: Self = Self(0);
+}
+
+impl<P: Primitive> Cell<P> {
+    #[inline]
+    fn is_empty(self) -> bool {
+        self.0 == P::from(EMPTY)
+    }
 
-    const fn _as_first_pass_symbol(self) -> char {
-        match self.0 {
+    fn _as_first_pass_symbol(self) -> char {
+        match u8::try_from(self.0.into_usize()).unwrap_or(u8::MAX) {
             EMPTY => '░',
             BOTTOM_LEFT => '▖',
             BOTTOM_RIGHT => '▗',
@@ -34,15 +101,15 @@ impl Cell {
     }
 }
 
-impl Default for Cell {
+impl<P: Primitive> Default for Cell<P> {
     #[inline]
     fn default() -> Self {
-        Self::EMPTY
+        Self(P::from(EMPTY))
     }
 }
 
 #[cfg(feature = "serde")]
-impl serde::Serialize for Cell {
+impl<P: serde::Serialize> serde::Serialize for Cell<P> {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -53,16 +120,18 @@ impl serde::Serialize for Cell {
 }
 
 #[cfg(feature = "serde")]
-impl<'a> serde::Deserialize<'a> for Cell {
+impl<'a, P: serde::Deserialize<'a>> serde::Deserialize<'a> for Cell<P> {
     #[inline]
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'a>,
     {
-        Primitive::deserialize(deserializer).map(Self)
+        P::deserialize(deserializer).map(Self)
     }
 }
 
+const EMPTY: u8 = 0;
+
 #[must_use]
 pub const fn capacity(width: usize, height: usize) -> usize {
     if width == 0 || height == 0 {
@@ -72,28 +141,41 @@ pub const fn capacity(width: usize, height: usize) -> usize {
     }
 }
 
-pub fn clear(cells: &mut [Cell]) {
-    cells.fill(Cell::EMPTY);
+/// Same as [`capacity()`], but `None` if it does not fit in [`usize`].
+#[must_use]
+pub const fn checked_capacity(width: usize, height: usize) -> Option<usize> {
+    if width == 0 || height == 0 {
+        Some(0)
+    } else if let (Some(num_columns), Some(num_rows)) =
+        (width.checked_add(1), height.checked_add(1))
+    {
+        num_columns.checked_mul(num_rows)
+    } else {
+        None
+    }
 }
 
-#[allow(clippy::transmute_ptr_to_ptr)]
-fn primitive_slice(cells: &mut [Cell]) -> &mut [Primitive] {
-    unsafe { mem::transmute(cells) }
+pub fn clear<P: Primitive>(cells: &mut [Cell<P>]) {
+    cells.fill(Cell::default());
 }
 
-const BOTTOM_LEFT: Primitive = 1 << 0;
-const BOTTOM_RIGHT: Primitive = 1 << 1;
-const TOP_LEFT: Primitive = 1 << 2;
-const TOP_RIGHT: Primitive = 1 << 3;
+const BOTTOM_LEFT: u8 = 1 << 0;
+const BOTTOM_RIGHT: u8 = 1 << 1;
+const TOP_LEFT: u8 = 1 << 2;
+const TOP_RIGHT: u8 = 1 << 3;
 
-fn first_pass(cells: &mut [Cell], width: usize, pixels: impl IntoIterator<Item = bool>) {
-    let (cells, mut cell_index, mut column_index) = (primitive_slice(cells), 0, 0);
+fn first_pass<P: Primitive>(
+    cells: &mut [Cell<P>],
+    width: usize,
+    pixels: impl IntoIterator<Item = bool>,
+) {
+    let (mut cell_index, mut column_index) = (0, 0);
     for pixel in pixels {
         if pixel {
-            cells[cell_index] |= BOTTOM_RIGHT;
-            cells[cell_index + 1] |= BOTTOM_LEFT;
-            cells[cell_index + 1 + width] |= TOP_RIGHT;
-            cells[cell_index + 2 + width] = TOP_LEFT;
+            cells[cell_index].0 |= P::from(BOTTOM_RIGHT);
+            cells[cell_index + 1].0 |= P::from(BOTTOM_LEFT);
+            cells[cell_index + 1 + width].0 |= P::from(TOP_RIGHT);
+            cells[cell_index + 2 + width].0 = P::from(TOP_LEFT);
         }
         if column_index + 1 == width {
             cell_index += 2;
@@ -105,58 +187,495 @@ fn first_pass(cells: &mut [Cell], width: usize, pixels: impl IntoIterator<Item =
     }
 }
 
-const DOUBLE: Primitive = 1 << 0;
+const DOUBLE: u8 = 1 << 0;
+const CONCAVE: u8 = 1 << 1;
+const SHIFT: u32 = 2;
+
+/// How saddles, vertices touched by two diagonally opposite filled pixels, are resolved.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Connectivity {
+    /// Diagonally touching filled pixels belong to separate shapes.
+    #[default]
+    Four,
+    /// Diagonally touching filled pixels belong to the same shape.
+    Eight,
+    /// Saddles of a scalar field are joined when the average of their four samples is at or
+    /// above the level, same as [`Self::Four`] for pixels.
+    Average,
+}
 
-fn second_pass(cells: &mut [Cell]) {
-    let (cells, mut vertex_index) = (primitive_slice(cells), 1);
-    for cell in cells {
-        if *cell == BOTTOM_LEFT
-            || *cell == BOTTOM_RIGHT
-            || *cell == TOP_LEFT
-            || *cell == TOP_RIGHT
-            || *cell == BOTTOM_LEFT | BOTTOM_RIGHT | TOP_LEFT
-            || *cell == BOTTOM_RIGHT | TOP_LEFT | TOP_RIGHT
-            || *cell == TOP_LEFT | TOP_RIGHT | BOTTOM_LEFT
-            || *cell == TOP_RIGHT | BOTTOM_LEFT | BOTTOM_RIGHT
+/// Flags of the vertex in a cell with `kind` of pixels around it, if it has one.
+fn flags(kind: u8, double: u8) -> Option<u8> {
+    match kind {
+        BOTTOM_LEFT | BOTTOM_RIGHT | TOP_LEFT | TOP_RIGHT => Some(0),
+        kind if kind == BOTTOM_LEFT | BOTTOM_RIGHT | TOP_LEFT
+            || kind == BOTTOM_RIGHT | TOP_LEFT | TOP_RIGHT
+            || kind == TOP_LEFT | TOP_RIGHT | BOTTOM_LEFT
+            || kind == TOP_RIGHT | BOTTOM_LEFT | BOTTOM_RIGHT =>
         {
-            *cell = vertex_index << 1;
-            vertex_index += 1;
-        } else if *cell == BOTTOM_LEFT | TOP_RIGHT || *cell == BOTTOM_RIGHT | TOP_LEFT {
-            *cell = (vertex_index << 1) ^ DOUBLE;
-            vertex_index += 1;
-        } else {
-            *cell = EMPTY;
+            Some(CONCAVE)
         }
+        kind if kind == BOTTOM_LEFT | TOP_RIGHT || kind == BOTTOM_RIGHT | TOP_LEFT => Some(double),
+        _ => None,
+    }
+}
+
+const fn double_flags(connectivity: Connectivity) -> u8 {
+    if matches!(connectivity, Connectivity::Eight) {
+        DOUBLE | CONCAVE
+    } else {
+        DOUBLE
     }
 }
 
+/// Largest vertex index cells of `P` can hold.
+fn max_index<P: Primitive>() -> usize {
+    (P::MAX >> SHIFT).into_usize()
+}
+
+/// Labels vertices starting from `first_index` and returns how many of them there are, failing
+/// once the last index is more than `P` can hold, with the vertices past it left unlabelled.
+fn try_label<P: Primitive>(
+    cells: &mut [Cell<P>],
+    double: u8,
+    first_index: usize,
+) -> Result<usize, Error> {
+    let (mut vertex_index, max) = (first_index, max_index::<P>());
+    for Cell(cell) in cells {
+        let kind = u8::try_from(cell.into_usize()).unwrap_or(u8::MAX);
+        let Some(flags) = flags(kind, double) else {
+            *cell = P::from(EMPTY);
+            continue;
+        };
+        *cell = if vertex_index <= max {
+            (P::from_usize(vertex_index) << SHIFT) | P::from(flags)
+        } else {
+            P::from(EMPTY)
+        };
+        vertex_index += 1;
+    }
+    match vertex_index - 1 {
+        len if len > max => Err(Error::VertexOverflow { len, max }),
+        _ => Ok(vertex_index - first_index),
+    }
+}
+
+/// Same as [`try_label()`], but panics instead of failing.
+fn label<P: Primitive>(cells: &mut [Cell<P>], double: u8, first_index: usize) -> usize {
+    try_label(cells, double, first_index).unwrap_or_else(|error| panic!("{error}"))
+}
+
 /// # Panics
-/// If `cells.len()` less than [`capacity()`]
-pub fn set(cells: &mut [Cell], width: usize, pixels: impl IntoIterator<Item = bool>) {
+/// If `cells.len()` less than [`capacity()`], or there are more vertices than `P` can index,
+/// `P::MAX >> 2` of them.
+pub fn set<P: Primitive>(
+    cells: &mut [Cell<P>],
+    width: usize,
+    pixels: impl IntoIterator<Item = bool>,
+    connectivity: Connectivity,
+) {
     first_pass(cells, width, pixels);
-    second_pass(cells);
+    label(cells, double_flags(connectivity), 1);
+}
+
+/// Pixels packed 64 to a [`u64`] word, the first one in the least significant bit, with every
+/// row starting at a new word.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Packed<'a> {
+    pub width: usize,
+    pub height: usize,
+    /// Number of words from the start of a row to the start of the next one.
+    pub stride: usize,
+    pub words: &'a [u64],
+}
+
+impl<'a> Packed<'a> {
+    /// Rows taking as few words as they can.
+    #[must_use]
+    pub const fn new(width: usize, height: usize, words: &'a [u64]) -> Self {
+        Self {
+            width,
+            height,
+            stride: width.div_ceil(u64::BITS as usize),
+            words,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_stride(mut self, stride: usize) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    /// Word `index` of row `y`, with pixels past the width or the height cleared.
+    fn word(&self, y: Option<usize>, index: usize) -> u64 {
+        let bits = u64::BITS as usize;
+        match y {
+            Some(y) if y < self.height && index * bits < self.width => {
+                let word = self.words[y * self.stride + index];
+                match self.width - index * bits {
+                    len if len < bits => word & ((1 << len) - 1),
+                    _ => word,
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    /// Every pixel one at a time, like [`set()`] takes them.
+    #[must_use]
+    pub const fn bits(self) -> Bits<'a> {
+        Bits {
+            packed: self,
+            index: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Bits<'a> {
+    packed: Packed<'a>,
+    index: usize,
+}
+
+impl Iterator for Bits<'_> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Packed {
+            width,
+            height,
+            stride,
+            words,
+        } = self.packed;
+        if self.index >= width * height {
+            return None;
+        }
+        let (y, x) = (self.index / width, self.index % width);
+        self.index += 1;
+        let bits = u64::BITS as usize;
+        Some(words[y * stride + x / bits] >> (x % bits) & 1 == 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.packed.width * self.packed.height).saturating_sub(self.index);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Bits<'_> {}
+
+impl FusedIterator for Bits<'_> {}
+
+/// Same as [`set()`], but tells which of 64 cells have a vertex at once from packed pixels,
+/// without going through them one at a time.
+///
+/// # Panics
+/// If `cells.len()` less than [`capacity()`], there are less words than the rows take, or more
+/// vertices than `P` can index.
+pub fn set_packed<P: Primitive>(cells: &mut [Cell<P>], packed: Packed, connectivity: Connectivity) {
+    if packed.width == 0 || packed.height == 0 {
+        return;
+    }
+    let num_cell_columns = packed.width + 1;
+    let num_words = num_cell_columns.div_ceil(u64::BITS as usize);
+    let (double, max) = (double_flags(connectivity), max_index::<P>());
+    let mut vertex_index = 1;
+    for (y, row) in cells
+        .chunks_mut(num_cell_columns)
+        .take(packed.height + 1)
+        .enumerate()
+    {
+        row.fill(Cell::default());
+        let (mut top_carry, mut bottom_carry) = (0, 0);
+        for index in 0..num_words {
+            // Bit `x` of each word tells whether the pixel at that corner of cell `x` is filled.
+            let (top_right, bottom_right) = (
+                packed.word(y.checked_sub(1), index),
+                packed.word(Some(y), index),
+            );
+            let (top_left, bottom_left) = (
+                (top_right << 1) | top_carry,
+                (bottom_right << 1) | bottom_carry,
+            );
+            (top_carry, bottom_carry) = (top_right >> 63, bottom_right >> 63);
+            let any = top_left | top_right | bottom_left | bottom_right;
+            let mut mixed = any & !(top_left & top_right & bottom_left & bottom_right);
+            while mixed != 0 {
+                let bit = mixed.trailing_zeros();
+                mixed &= mixed - 1;
+                let x = index * u64::BITS as usize + bit as usize;
+                let kind = [
+                    (bottom_left, BOTTOM_LEFT),
+                    (bottom_right, BOTTOM_RIGHT),
+                    (top_left, TOP_LEFT),
+                    (top_right, TOP_RIGHT),
+                ]
+                .into_iter()
+                .fold(0, |kind, (word, corner)| {
+                    if word >> bit & 1 == 1 {
+                        kind | corner
+                    } else {
+                        kind
+                    }
+                });
+                if let Some(flags) = flags(kind, double) {
+                    assert!(
+                        vertex_index <= max,
+                        "{}",
+                        Error::VertexOverflow {
+                            len: vertex_index,
+                            max
+                        }
+                    );
+                    row[x] = Cell((P::from_usize(vertex_index) << SHIFT) | P::from(flags));
+                    vertex_index += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Same as [`set()`], but over rows of cells in parallel, labelling the same vertices.
+///
+/// # Panics
+/// If `cells.len()` less than [`capacity()`], or there are more vertices than `P` can index.
+#[cfg(feature = "rayon")]
+#[cfg_attr(doc, doc(cfg(feature = "rayon")))]
+pub fn par_set<P: Primitive>(
+    cells: &mut [Cell<P>],
+    width: usize,
+    pixels: &[bool],
+    connectivity: Connectivity,
+) {
+    use ::alloc::vec::Vec;
+    use rayon::prelude::*;
+
+    if width == 0 || pixels.is_empty() {
+        return;
+    }
+    let num_cell_columns = width + 1;
+    let num_cell_rows = pixels.len().div_ceil(width) + 1;
+    let cells = &mut cells[..num_cell_columns * num_cell_rows];
+    let pixel = |x: Option<usize>, y: Option<usize>| match (x, y) {
+        (Some(x), Some(y)) if x < width => pixels.get(y * width + x) == Some(&true),
+        _ => false,
+    };
+    let (double, mut num_vertices) = (double_flags(connectivity), Vec::new());
+    cells
+        .par_chunks_mut(num_cell_columns)
+        .enumerate()
+        .map(|(y, row)| {
+            let mut num_row_vertices = 0;
+            for (x, Cell(cell)) in row.iter_mut().enumerate() {
+                let (left, top) = (x.checked_sub(1), y.checked_sub(1));
+                let kind = [
+                    (pixel(left, Some(y)), BOTTOM_LEFT),
+                    (pixel(Some(x), Some(y)), BOTTOM_RIGHT),
+                    (pixel(left, top), TOP_LEFT),
+                    (pixel(Some(x), top), TOP_RIGHT),
+                ]
+                .into_iter()
+                .fold(
+                    0,
+                    |kind, (is_filled, corner)| {
+                        if is_filled {
+                            kind | corner
+                        } else {
+                            kind
+                        }
+                    },
+                );
+                *cell = P::from(kind);
+                num_row_vertices += usize::from(flags(kind, double).is_some());
+            }
+            num_row_vertices
+        })
+        .collect_into_vec(&mut num_vertices);
+    let mut first_index = 1;
+    for num_row_vertices in &mut num_vertices {
+        first_index += *num_row_vertices;
+        *num_row_vertices = first_index - *num_row_vertices;
+    }
+    let (len, max) = (first_index - 1, max_index::<P>());
+    assert!(len <= max, "{}", Error::VertexOverflow { len, max });
+    cells
+        .par_chunks_mut(num_cell_columns)
+        .zip(num_vertices)
+        .for_each(|(row, first_index)| {
+            label(row, double, first_index);
+        });
+}
+
+/// Same as the indices of [`get()`] chained together, horizontal ones by row and vertical ones
+/// by column in parallel.
+#[cfg(feature = "rayon")]
+#[cfg_attr(doc, doc(cfg(feature = "rayon")))]
+pub fn par_indices<P: Primitive>(
+    cells: &[Cell<P>],
+    width: usize,
+) -> impl rayon::iter::ParallelIterator<Item = [P; 2]> + '_ {
+    use rayon::prelude::*;
+
+    let num_cell_columns = width + 1;
+    let horizontal_indices =
+        cells
+            .par_chunks(num_cell_columns)
+            .flat_map_iter(|row| HorizontalIndices {
+                cells: row,
+                maybe_prev: None,
+            });
+    let vertical_indices = (0..num_cell_columns)
+        .into_par_iter()
+        .flat_map_iter(move |column| {
+            let mut maybe_prev = None;
+            cells
+                .get(column..)
+                .unwrap_or_default()
+                .iter()
+                .step_by(num_cell_columns)
+                .filter_map(move |&cell| next_index_pair(&mut maybe_prev, cell))
+        });
+    horizontal_indices.chain(vertical_indices)
+}
+
+/// Why [`try_set()`] failed.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Error {
+    /// Number of cells does not fit in [`usize`], or coordinates do not fit in [`Primitive`].
+    DimensionsOverflow { width: usize, height: usize },
+    /// There are less cells than [`capacity()`].
+    CellsTooFew { len: usize, capacity: usize },
+    /// [`capacity()`] is more than the most cells allowed.
+    CellsTooMany { len: usize, max: usize },
+    /// Cells could not be allocated.
+    AllocationFailed { len: usize },
+    /// There are less than `width * height` pixels.
+    PixelsTooFew { len: usize, expected: usize },
+    /// There are more than `width * height` pixels.
+    PixelsTooMany { expected: usize },
+    /// There are more vertices than [`Primitive`] can index.
+    VertexOverflow { len: usize, max: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::DimensionsOverflow { width, height } => {
+                write!(f, "{width}x{height} image is too large")
+            }
+            Self::CellsTooFew { len, capacity } => {
+                write!(f, "{len} cells are less than capacity of {capacity}")
+            }
+            Self::CellsTooMany { len, max } => {
+                write!(f, "{len} cells are more than allowed {max}")
+            }
+            Self::AllocationFailed { len } => write!(f, "failed to allocate {len} cells"),
+            Self::PixelsTooFew { len, expected } => {
+                write!(f, "{len} pixels are less than expected {expected}")
+            }
+            Self::PixelsTooMany { expected } => {
+                write!(f, "pixels are more than expected {expected}")
+            }
+            Self::VertexOverflow { len, max } => {
+                write!(f, "{len} vertices are more than indexable {max}")
+            }
+        }
+    }
+}
+
+impl ::core::error::Error for Error {}
+
+/// Same as [`set()`], but checks the arguments instead of panicking, wrapping indices or ignoring
+/// extra pixels. `cells` are left in an unspecified state on error.
+///
+/// # Errors
+/// See [`Error`]
+pub fn try_set<P: Primitive>(
+    cells: &mut [Cell<P>],
+    width: usize,
+    height: usize,
+    pixels: impl IntoIterator<Item = bool>,
+    connectivity: Connectivity,
+) -> Result<(), Error> {
+    let capacity = checked_capacity(width, height)
+        .filter(|_| width.max(height) <= P::MAX.into_usize())
+        .ok_or(Error::DimensionsOverflow { width, height })?;
+    if cells.len() < capacity {
+        return Err(Error::CellsTooFew {
+            len: cells.len(),
+            capacity,
+        });
+    }
+    let (mut pixels, expected, mut len) = (pixels.into_iter(), width * height, 0);
+    first_pass(
+        cells,
+        width,
+        pixels.by_ref().take(expected).inspect(|_| len += 1),
+    );
+    if len < expected {
+        return Err(Error::PixelsTooFew { len, expected });
+    }
+    if pixels.next().is_some() {
+        return Err(Error::PixelsTooMany { expected });
+    }
+    try_label(cells, double_flags(connectivity), 1)?;
+    Ok(())
+}
+
+/// Resolves every saddle labelled by [`set()`] again, joining the diagonally touching pixels
+/// around the vertex at `[x, y]` when `is_joined` returns `true`.
+pub fn connect<P: Primitive>(
+    cells: &mut [Cell<P>],
+    width: usize,
+    mut is_joined: impl FnMut([usize; 2]) -> bool,
+) {
+    let num_cell_columns = width + 1;
+    for (cell_index, Cell(cell)) in cells.iter_mut().enumerate() {
+        if *cell & P::from(DOUBLE) != P::from(EMPTY) {
+            let vertex = [cell_index % num_cell_columns, cell_index / num_cell_columns];
+            *cell = if is_joined(vertex) {
+                *cell | P::from(CONCAVE)
+            } else {
+                *cell & !P::from(CONCAVE)
+            };
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default)]
-pub struct Vertices<'a> {
-    cells: &'a [Cell],
-    num_cell_columns: Primitive,
-    cell_column_index: Primitive,
-    cell_row_index: Primitive,
+pub struct Vertices<'a, P = u16> {
+    cells: &'a [Cell<P>],
+    num_cell_columns: usize,
+    cell_column_index: usize,
+    cell_row_index: usize,
+    origin: [usize; 2],
+}
+
+impl<P> Vertices<'_, P> {
+    /// Offsets every vertex by `origin`, which has to keep them within [`Primitive`].
+    #[must_use]
+    pub const fn with_origin(mut self, origin: [usize; 2]) -> Self {
+        self.origin = origin;
+        self
+    }
 }
 
-impl<'a> Iterator for Vertices<'a> {
-    type Item = [Primitive; 2];
+impl<P: Primitive> Iterator for Vertices<'_, P> {
+    type Item = [P; 2];
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (cell_index, &Cell(cell)) in self.cells.iter().enumerate() {
-            let maybe_vertex = [self.cell_column_index, self.cell_row_index];
+        for (cell_index, &cell) in self.cells.iter().enumerate() {
+            let maybe_vertex = [
+                P::from_usize(self.origin[0] + self.cell_column_index),
+                P::from_usize(self.origin[1] + self.cell_row_index),
+            ];
             self.cell_column_index += 1;
             if self.cell_column_index == self.num_cell_columns {
                 self.cell_column_index = 0;
                 self.cell_row_index += 1;
             }
-            if cell != EMPTY {
+            if !cell.is_empty() {
                 self.cells = &self.cells[cell_index + 1..];
                 return Some(maybe_vertex);
             }
@@ -169,35 +688,42 @@ impl<'a> Iterator for Vertices<'a> {
     }
 }
 
-impl<'a> FusedIterator for Vertices<'a> {}
+impl<P: Primitive> FusedIterator for Vertices<'_, P> {}
+
+/// Index of the vertex labelled in a non-empty `cell`.
+fn index<P: Primitive>(cell: P) -> P {
+    (cell >> SHIFT) - P::from(1)
+}
 
-fn next_index_pair(maybe_prev_index: &mut Primitive, cell: Primitive) -> Option<[Primitive; 2]> {
-    if cell == EMPTY {
+fn next_index_pair<P: Primitive>(maybe_prev_cell: &mut Option<P>, cell: Cell<P>) -> Option<[P; 2]> {
+    if cell.is_empty() {
         None
     } else {
-        let index = cell >> 1;
-        if *maybe_prev_index == EMPTY {
-            *maybe_prev_index = index;
-            None
-        } else if cell & DOUBLE != 0 {
-            Some([mem::replace(maybe_prev_index, index) - 1, index - 1])
-        } else {
-            Some([mem::take(maybe_prev_index) - 1, index - 1])
+        let Cell(cell) = cell;
+        match *maybe_prev_cell {
+            None => {
+                *maybe_prev_cell = Some(cell);
+                None
+            }
+            Some(prev_cell) => {
+                *maybe_prev_cell = (cell & P::from(DOUBLE) != P::from(EMPTY)).then_some(cell);
+                Some([index(prev_cell), index(cell)])
+            }
         }
     }
 }
 
 #[derive(Clone, Debug, Default)]
-pub struct HorizontalIndices<'a> {
-    cells: &'a [Cell],
-    maybe_prev: Primitive,
+pub struct HorizontalIndices<'a, P = u16> {
+    cells: &'a [Cell<P>],
+    maybe_prev: Option<P>,
 }
 
-impl<'a> Iterator for HorizontalIndices<'a> {
-    type Item = [Primitive; 2];
+impl<P: Primitive> Iterator for HorizontalIndices<'_, P> {
+    type Item = [P; 2];
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (cell_index, &Cell(cell)) in self.cells.iter().enumerate() {
+        for (cell_index, &cell) in self.cells.iter().enumerate() {
             let maybe_index_pair = next_index_pair(&mut self.maybe_prev, cell);
             if maybe_index_pair.is_some() {
                 self.cells = &self.cells[cell_index + 1..];
@@ -212,32 +738,30 @@ impl<'a> Iterator for HorizontalIndices<'a> {
     }
 }
 
-impl<'a> FusedIterator for HorizontalIndices<'a> {}
+impl<P: Primitive> FusedIterator for HorizontalIndices<'_, P> {}
 
 #[derive(Clone, Debug, Default)]
-pub struct VerticalIndices<'a> {
-    cells: &'a [Cell],
-    num_cell_columns: Primitive,
-    cell_column_index: Primitive,
-    cell_row_index: Primitive,
-    maybe_prev: Primitive,
+pub struct VerticalIndices<'a, P = u16> {
+    cells: &'a [Cell<P>],
+    num_cell_columns: usize,
+    cell_column_index: usize,
+    cell_row_index: usize,
+    maybe_prev: Option<P>,
 }
 
-impl<'a> Iterator for VerticalIndices<'a> {
-    type Item = [Primitive; 2];
+impl<P: Primitive> Iterator for VerticalIndices<'_, P> {
+    type Item = [P; 2];
 
     fn next(&mut self) -> Option<Self::Item> {
         for cell_column_index in self.cell_column_index..self.num_cell_columns {
-            for cell_index in (cell_column_index as _..self.cells.len())
-                .step_by(self.num_cell_columns as _)
-                .skip(self.cell_row_index as _)
+            for cell_index in (cell_column_index..self.cells.len())
+                .step_by(self.num_cell_columns)
+                .skip(self.cell_row_index)
             {
                 let maybe_index_pair =
-                    next_index_pair(&mut self.maybe_prev, self.cells[cell_index].0);
+                    next_index_pair(&mut self.maybe_prev, self.cells[cell_index]);
                 if maybe_index_pair.is_some() {
-                    if (self.cells.len() as Primitive)
-                        <= cell_index as Primitive + self.num_cell_columns
-                    {
+                    if self.cells.len() <= cell_index + self.num_cell_columns {
                         self.cell_column_index = cell_column_index + 1;
                         self.cell_row_index = 0;
                     } else {
@@ -255,33 +779,705 @@ impl<'a> Iterator for VerticalIndices<'a> {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let num_cell_rows = (self.cells.len() as Primitive).saturating_div(self.num_cell_columns);
+        let num_cell_rows = self
+            .cells
+            .len()
+            .checked_div(self.num_cell_columns)
+            .unwrap_or(0);
         let upper_bound =
             (self.num_cell_columns - self.cell_column_index) * num_cell_rows - self.cell_row_index;
-        (0, Some(upper_bound as _))
+        (0, Some(upper_bound))
     }
 }
 
-impl<'a> FusedIterator for VerticalIndices<'a> {}
+impl<P: Primitive> FusedIterator for VerticalIndices<'_, P> {}
 
 #[must_use]
-pub const fn get(cells: &[Cell], width: usize) -> (Vertices, HorizontalIndices, VerticalIndices) {
+pub const fn get<P>(
+    cells: &[Cell<P>],
+    width: usize,
+) -> (
+    Vertices<'_, P>,
+    HorizontalIndices<'_, P>,
+    VerticalIndices<'_, P>,
+) {
     let vertices = Vertices {
         cells,
-        num_cell_columns: width as Primitive + 1,
+        num_cell_columns: width + 1,
         cell_column_index: 0,
         cell_row_index: 0,
+        origin: [0, 0],
     };
     let horizontal_indices = HorizontalIndices {
         cells,
-        maybe_prev: 0,
+        maybe_prev: None,
     };
     let vertical_indices = VerticalIndices {
         cells,
-        num_cell_columns: width as Primitive + 1,
+        num_cell_columns: width + 1,
         cell_column_index: 0,
         cell_row_index: 0,
-        maybe_prev: 0,
+        maybe_prev: None,
     };
     (vertices, horizontal_indices, vertical_indices)
 }
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum RingVertex<P = u16> {
+    /// First vertex of a new ring, the top-left-most one.
+    Start(P),
+    /// Next vertex of the current ring.
+    Next(P),
+}
+
+impl<P: Copy> RingVertex<P> {
+    #[inline]
+    #[must_use]
+    pub const fn index(self) -> P {
+        match self {
+            Self::Start(index) | Self::Next(index) => index,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn is_start(self) -> bool {
+        matches!(self, Self::Start(_))
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+enum Heading {
+    East,
+    South,
+    West,
+    North,
+}
+
+impl Heading {
+    const fn clockwise(self) -> Self {
+        match self {
+            Self::East => Self::South,
+            Self::South => Self::West,
+            Self::West => Self::North,
+            Self::North => Self::East,
+        }
+    }
+
+    const fn counter_clockwise(self) -> Self {
+        match self {
+            Self::East => Self::North,
+            Self::South => Self::East,
+            Self::West => Self::South,
+            Self::North => Self::West,
+        }
+    }
+
+    const fn step(self, cell_index: usize, num_cell_columns: usize) -> usize {
+        match self {
+            Self::East => cell_index.wrapping_add(1),
+            Self::South => cell_index.wrapping_add(num_cell_columns),
+            Self::West => cell_index.wrapping_sub(1),
+            Self::North => cell_index.wrapping_sub(num_cell_columns),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Rings<'a, P = u16> {
+    cells: &'a mut [Cell<P>],
+    num_cell_columns: usize,
+    cell_index: usize,
+    ring: Option<(usize, usize, Heading)>,
+}
+
+impl<P: Primitive> Rings<'_, P> {
+    fn next_vertex(
+        &mut self,
+        start: usize,
+        current: usize,
+        heading: Heading,
+    ) -> Option<RingVertex<P>> {
+        let mut cell_index = current;
+        loop {
+            cell_index = heading.step(cell_index, self.num_cell_columns);
+            let cell = *self.cells.get(cell_index)?;
+            if cell.is_empty() {
+                continue;
+            }
+            if cell_index == start {
+                self.cells[cell_index] = Cell::default();
+                return None;
+            }
+            let Cell(cell) = cell;
+            self.cells[cell_index] = if cell & P::from(DOUBLE) == P::from(EMPTY) {
+                Cell::default()
+            } else {
+                Cell(cell ^ P::from(DOUBLE))
+            };
+            let heading = if cell & P::from(CONCAVE) == P::from(EMPTY) {
+                heading.clockwise()
+            } else {
+                heading.counter_clockwise()
+            };
+            self.ring = Some((start, cell_index, heading));
+            return Some(RingVertex::Next(index(cell)));
+        }
+    }
+}
+
+impl<P: Primitive> Iterator for Rings<'_, P> {
+    type Item = RingVertex<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((start, current, heading)) = self.ring {
+            let maybe_vertex = self.next_vertex(start, current, heading);
+            if maybe_vertex.is_some() {
+                return maybe_vertex;
+            }
+            self.ring = None;
+        }
+        for (cell_index, &Cell(cell)) in self.cells.iter().enumerate().skip(self.cell_index) {
+            if cell != P::from(EMPTY) {
+                let heading = if cell & P::from(CONCAVE) == P::from(EMPTY) {
+                    Heading::East
+                } else {
+                    Heading::South
+                };
+                self.cell_index = cell_index;
+                self.ring = Some((cell_index, cell_index, heading));
+                return Some(RingVertex::Start(index(cell)));
+            }
+        }
+        self.cell_index = self.cells.len();
+        None
+    }
+}
+
+impl<P: Primitive> FusedIterator for Rings<'_, P> {}
+
+/// Walks the boundaries labelled by [`set()`] as closed rings of [`Vertices`] indices.
+///
+/// Each ring starts at its top-left-most vertex and keeps filled pixels on its right-hand
+/// side as seen on screen (rows going down), so outer boundaries are counter-clockwise and
+/// holes are clockwise when `y` is treated as pointing up. Saddle vertices are passed twice,
+/// turning around the filled pixels when they are separate and around the empty ones when they
+/// are joined, so a ring never crosses a diagonal.
+///
+/// Vertices are cleared from `cells` as they are yielded, so [`get()`] has to be used before.
+#[must_use]
+pub fn trace<P>(cells: &mut [Cell<P>], width: usize) -> Rings<'_, P> {
+    Rings {
+        cells,
+        num_cell_columns: width + 1,
+        cell_index: 0,
+        ring: None,
+    }
+}
+
+/// Labelling and tracing of several nested levels in a single walk over the grid.
+#[cfg(feature = "alloc")]
+pub(crate) mod levels {
+    use super::{
+        double_flags, flags, max_index, Connectivity, Error, Heading, Primitive, RingVertex,
+        BOTTOM_LEFT, BOTTOM_RIGHT, CONCAVE, DOUBLE, EMPTY, TOP_LEFT, TOP_RIGHT,
+    };
+    use ::alloc::{vec, vec::Vec};
+    use ::core::iter;
+
+    /// Vertices of one of the levels labelled by [`set()`], row by row, with the flags of their
+    /// cells.
+    #[derive(Clone, Debug, Default)]
+    pub(crate) struct Level<P> {
+        vertices: Vec<[P; 2]>,
+        flags: Vec<u8>,
+    }
+
+    /// Same as [`super::set()`] at every one of `num_levels` levels at once, with pixels filled at
+    /// the levels below their band. Saddles are joined when `is_joined(level, [x, y])` returns
+    /// `true` with [`Connectivity::Average`].
+    ///
+    /// # Panics
+    /// If there are less than `width * height` bands, or more vertices at a level than `P` can
+    /// index
+    pub(crate) fn set<P: Primitive>(
+        bands: &[usize],
+        [width, height]: [usize; 2],
+        num_levels: usize,
+        connectivity: Connectivity,
+        mut is_joined: impl FnMut(usize, [usize; 2]) -> bool,
+    ) -> Vec<Level<P>> {
+        let mut levels = vec![Level::default(); num_levels];
+        if width == 0 || height == 0 {
+            return levels;
+        }
+        let (double, max) = (double_flags(connectivity), max_index::<P>());
+        let band = |x: Option<usize>, y: Option<usize>| match (x, y) {
+            (Some(x), Some(y)) if x < width && y < height => bands[y * width + x],
+            _ => 0,
+        };
+        for y in 0..=height {
+            for x in 0..=width {
+                let (left, top) = (x.checked_sub(1), y.checked_sub(1));
+                let corners = [
+                    (band(left, Some(y)), BOTTOM_LEFT),
+                    (band(Some(x), Some(y)), BOTTOM_RIGHT),
+                    (band(left, top), TOP_LEFT),
+                    (band(Some(x), top), TOP_RIGHT),
+                ];
+                let lowest = corners.iter().map(|&(band, _)| band).min().unwrap_or(0);
+                let highest = corners.iter().map(|&(band, _)| band).max().unwrap_or(0);
+                // Every corner is filled below the lowest band and empty from the highest one.
+                for (index, level) in levels.iter_mut().enumerate().take(highest).skip(lowest) {
+                    let kind = corners
+                        .into_iter()
+                        .filter(|&(band, _)| band > index)
+                        .fold(0, |kind, (_, corner)| kind | corner);
+                    let Some(mut flags) = flags(kind, double) else {
+                        continue;
+                    };
+                    if connectivity == Connectivity::Average
+                        && flags & DOUBLE != EMPTY
+                        && is_joined(index, [x, y])
+                    {
+                        flags |= CONCAVE;
+                    }
+                    let len = level.vertices.len() + 1;
+                    assert!(len <= max, "{}", Error::VertexOverflow { len, max });
+                    level.vertices.push([P::from_usize(x), P::from_usize(y)]);
+                    level.flags.push(flags);
+                }
+            }
+        }
+        levels
+    }
+
+    impl<P: Primitive> Level<P> {
+        /// Same as [`super::trace()`], but stepping from vertex to vertex, with the vertices the
+        /// yielded indices point to.
+        pub(crate) fn trace(self) -> (Vec<[P; 2]>, impl Iterator<Item = RingVertex<P>>) {
+            let Self { vertices, flags } = self;
+            let mut columns: Vec<usize> = (0..vertices.len()).collect();
+            columns.sort_unstable_by_key(|&index| vertices[index]);
+            let mut positions = vec![0; vertices.len()];
+            for (position, &index) in columns.iter().enumerate() {
+                positions[index] = position;
+            }
+            let step = move |index: usize, heading: Heading| match heading {
+                Heading::East => index + 1,
+                Heading::South => columns[positions[index] + 1],
+                Heading::West => index - 1,
+                Heading::North => columns[positions[index] - 1],
+            };
+            let turn = |flags: u8, heading: Heading| {
+                if flags & CONCAVE == EMPTY {
+                    heading.clockwise()
+                } else {
+                    heading.counter_clockwise()
+                }
+            };
+            let mut flags: Vec<_> = flags.into_iter().map(Some).collect();
+            let (mut start_index, mut ring) = (0, None);
+            let ring_vertices = iter::from_fn(move || {
+                if let Some((start, current, heading)) = ring.take() {
+                    let index = step(current, heading);
+                    if index != start {
+                        let vertex_flags = flags[index]?;
+                        flags[index] =
+                            (vertex_flags & DOUBLE != EMPTY).then_some(vertex_flags ^ DOUBLE);
+                        ring = Some((start, index, turn(vertex_flags, heading)));
+                        return Some(RingVertex::Next(P::from_usize(index)));
+                    }
+                    flags[start] = None;
+                }
+                let index = start_index + flags[start_index..].iter().position(Option::is_some)?;
+                let heading = if flags[index]? & CONCAVE == EMPTY {
+                    Heading::East
+                } else {
+                    Heading::South
+                };
+                (start_index, ring) = (index, Some((index, index, heading)));
+                Some(RingVertex::Start(P::from_usize(index)))
+            });
+            (vertices, ring_vertices)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+pub(crate) mod tests {
+    use super::*;
+    use ::alloc::{vec, vec::Vec};
+    #[cfg(feature = "rayon")]
+    use rayon::prelude::*;
+
+    /// Pixels filled by an xorshift generator, about half of them.
+    pub(crate) fn pixels(seed: u64, len: usize) -> Vec<bool> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state & 1 == 1
+            })
+            .collect()
+    }
+
+    /// Rings as lists of vertices, each starting at its smallest vertex, sorted.
+    pub(crate) fn canonical<P: Ord + Copy>(rings: impl IntoIterator<Item = Vec<P>>) -> Vec<Vec<P>> {
+        let mut rings: Vec<_> = rings
+            .into_iter()
+            .map(|mut ring| {
+                let start = (0..ring.len()).min_by_key(|&position| ring[position]);
+                ring.rotate_left(start.unwrap_or(0));
+                ring
+            })
+            .collect();
+        rings.sort_unstable();
+        rings
+    }
+
+    /// Rings of the whole image as [`trace()`] yields them, made [`canonical()`].
+    pub(crate) fn rings(
+        width: usize,
+        height: usize,
+        pixels: &[bool],
+        connectivity: Connectivity,
+    ) -> Vec<Vec<[u16; 2]>> {
+        let mut cells = vec![Cell::<u16>::default(); capacity(width, height)];
+        set(&mut cells, width, pixels.iter().copied(), connectivity);
+        let vertices: Vec<_> = get(&cells, width).0.collect();
+        let mut rings = Vec::new();
+        for vertex in trace(&mut cells, width) {
+            match vertex {
+                RingVertex::Start(index) => rings.push(vec![vertices[usize::from(index)]]),
+                RingVertex::Next(index) => {
+                    if let Some(ring) = rings.last_mut() {
+                        ring.push(vertices[usize::from(index)]);
+                    }
+                }
+            }
+        }
+        canonical(rings)
+    }
+
+    /// Components of filled pixels labelled by a breadth-first search, in the order of their
+    /// first pixels.
+    pub(crate) fn components(
+        width: usize,
+        height: usize,
+        pixels: &[bool],
+        connectivity: Connectivity,
+    ) -> Vec<Option<usize>> {
+        let mut labels = vec![None; pixels.len()];
+        let mut num_components = 0;
+        for start in 0..pixels.len() {
+            if !pixels[start] || labels[start].is_some() {
+                continue;
+            }
+            labels[start] = Some(num_components);
+            let mut queue = ::alloc::collections::VecDeque::from([start]);
+            while let Some(index) = queue.pop_front() {
+                let (x, y) = ((index % width).cast_signed(), (index / width).cast_signed());
+                for (dx, dy) in [
+                    (-1, -1),
+                    (0, -1),
+                    (1, -1),
+                    (-1, 0),
+                    (1, 0),
+                    (-1, 1),
+                    (0, 1),
+                    (1, 1),
+                ] {
+                    if dx != 0 && dy != 0 && connectivity != Connectivity::Eight {
+                        continue;
+                    }
+                    let (Ok(x), Ok(y)) = (usize::try_from(x + dx), usize::try_from(y + dy)) else {
+                        continue;
+                    };
+                    let neighbour = y * width + x;
+                    if x < width && y < height && pixels[neighbour] && labels[neighbour].is_none() {
+                        labels[neighbour] = Some(num_components);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+            num_components += 1;
+        }
+        labels
+    }
+
+    /// Area of a ring, positive when it keeps the inside on its right-hand side on screen.
+    pub(crate) fn signed_area(ring: &[[u16; 2]]) -> i64 {
+        let twice_area: i64 = ring
+            .iter()
+            .zip(ring.iter().cycle().skip(1))
+            .map(|(&[x0, y0], &[x1, y1])| {
+                i64::from(x0) * i64::from(y1) - i64::from(x1) * i64::from(y0)
+            })
+            .sum();
+        twice_area / 2
+    }
+
+    /// Whether the center of the pixel at `[x, y]` is inside `ring`, crossing its edges to the
+    /// left of it.
+    pub(crate) fn is_inside(ring: &[[u16; 2]], [x, y]: [usize; 2]) -> bool {
+        let center = [2 * x + 1, 2 * y + 1];
+        let mut is_inside = false;
+        for (&from, &to) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            let [from, to] = [from, to].map(|vertex| vertex.map(|value| 2 * usize::from(value)));
+            let crosses = (from[1] < center[1]) != (to[1] < center[1]);
+            if crosses && from[0] < center[0] {
+                is_inside = !is_inside;
+            }
+        }
+        is_inside
+    }
+
+    #[test]
+    fn traced_rings_enclose_their_pixels() {
+        for (seed, (width, height)) in (1..).zip([(1, 1), (2, 2), (5, 3), (17, 13), (40, 31)]) {
+            let pixels = pixels(seed, width * height);
+            for (connectivity, dual) in [
+                (Connectivity::Four, Connectivity::Eight),
+                (Connectivity::Eight, Connectivity::Four),
+            ] {
+                let rings = rings(width, height, &pixels, connectivity);
+                for ring in &rings {
+                    let num_inside = (0..width * height)
+                        .filter(|&index| is_inside(ring, [index % width, index / width]))
+                        .count();
+                    let area = signed_area(ring).unsigned_abs();
+                    assert_eq!(area, u64::try_from(num_inside).unwrap(), "{ring:?}");
+                }
+                let num_filled = pixels.iter().filter(|&&is_filled| is_filled).count();
+                let area: i64 = rings.iter().map(|ring| signed_area(ring)).sum();
+                assert_eq!(area, i64::try_from(num_filled).unwrap());
+
+                let num_outers = rings.iter().filter(|ring| signed_area(ring) > 0).count();
+                let labels = components(width, height, &pixels, connectivity);
+                assert_eq!(
+                    num_outers,
+                    labels.iter().flatten().max().map_or(0, |max| max + 1)
+                );
+                let empty: Vec<bool> = pixels.iter().map(|&is_filled| !is_filled).collect();
+                let mut lakes = components(width, height, &empty, dual);
+                for index in 0..width * height {
+                    let (x, y) = (index % width, index / width);
+                    if x == 0 || y == 0 || x + 1 == width || y + 1 == height {
+                        let sea = lakes[index];
+                        lakes
+                            .iter_mut()
+                            .filter(|label| **label == sea)
+                            .for_each(|label| *label = None);
+                    }
+                }
+                lakes.sort_unstable();
+                lakes.dedup();
+                let num_holes = rings.iter().filter(|ring| signed_area(ring) < 0).count();
+                assert_eq!(
+                    num_holes,
+                    lakes.iter().flatten().count(),
+                    "{connectivity:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn trace_resolves_saddles() {
+        let diagonal = [true, false, false, true];
+        assert_eq!(
+            rings(2, 2, &diagonal, Connectivity::Four),
+            [
+                vec![[0, 0], [1, 0], [1, 1], [0, 1]],
+                vec![[1, 1], [2, 1], [2, 2], [1, 2]]
+            ]
+        );
+        assert_eq!(
+            rings(2, 2, &diagonal, Connectivity::Eight),
+            [vec![
+                [0, 0],
+                [1, 0],
+                [1, 1],
+                [2, 1],
+                [2, 2],
+                [1, 2],
+                [1, 1],
+                [0, 1]
+            ]]
+        );
+        let mut lakes = [true; 16];
+        (lakes[5], lakes[10]) = (false, false);
+        let holes = |connectivity| {
+            let rings = rings(4, 4, &lakes, connectivity);
+            let holes: Vec<_> = rings
+                .into_iter()
+                .filter(|ring| signed_area(ring) < 0)
+                .collect();
+            holes.iter().map(Vec::len).collect::<Vec<_>>()
+        };
+        assert_eq!(holes(Connectivity::Four), [8]);
+        assert_eq!(holes(Connectivity::Eight), [4, 4]);
+    }
+
+    #[test]
+    fn connectivity_joins_diagonal_line() {
+        let line: Vec<bool> = (0..25).map(|index| index % 6 == 0).collect();
+        let four = rings(5, 5, &line, Connectivity::Four);
+        assert_eq!(four.len(), 5);
+        assert!(four
+            .iter()
+            .all(|ring| ring.len() == 4 && signed_area(ring) == 1));
+        let eight = rings(5, 5, &line, Connectivity::Eight);
+        assert_eq!(eight.len(), 1);
+        assert_eq!((eight[0].len(), signed_area(&eight[0])), (4 * 5, 5));
+    }
+
+    /// Pixels packed into words of rows `stride` words apart, as [`Packed`] reads them.
+    pub(crate) fn words(width: usize, pixels: &[bool], stride: usize) -> Vec<u64> {
+        let mut words = vec![0; pixels.len().div_ceil(width.max(1)) * stride];
+        for (index, _) in pixels
+            .iter()
+            .enumerate()
+            .filter(|&(_, &is_filled)| is_filled)
+        {
+            let (x, y) = (index % width, index / width);
+            words[y * stride + x / 64] |= 1 << (x % 64);
+        }
+        words
+    }
+
+    #[test]
+    fn set_packed_matches_set() {
+        for (seed, (width, height)) in (1..).zip(
+            [1, 2, 7]
+                .into_iter()
+                .flat_map(|height| [1, 63, 64, 65, 128].map(|width| (width, height))),
+        ) {
+            let pixels = pixels(seed, width * height);
+            let strides = [width.div_ceil(64), width.div_ceil(64) + 2];
+            for (stride, connectivity) in strides.into_iter().flat_map(|stride| {
+                [Connectivity::Four, Connectivity::Eight].map(|connectivity| (stride, connectivity))
+            }) {
+                let mut cells = vec![Cell::<u32>::default(); capacity(width, height)];
+                set(&mut cells, width, pixels.iter().copied(), connectivity);
+                let words = words(width, &pixels, stride);
+                let packed = Packed::new(width, height, &words).with_stride(stride);
+                let mut packed_cells = vec![Cell::<u32>::default(); capacity(width, height)];
+                set_packed(&mut packed_cells, packed, connectivity);
+                assert_eq!(
+                    cells, packed_cells,
+                    "{width}x{height} {stride} {connectivity:?}"
+                );
+            }
+        }
+    }
+
+    /// Image of `len` pixels apart from each other, 4 vertices each, followed by a pair of
+    /// diagonally touching ones sharing a saddle, 7 vertices, when `has_saddle`.
+    fn isolated(len: usize, has_saddle: bool) -> (usize, usize, Vec<bool>) {
+        let (width, mut height) = (130, len.div_ceil(65) * 2);
+        let mut pixels = vec![false; width * height];
+        for index in 0..len {
+            pixels[index / 65 * 2 * width + index % 65 * 2] = true;
+        }
+        if has_saddle {
+            pixels.extend((0..2 * width).map(|index| index == 0 || index == width + 1));
+            height += 2;
+        }
+        (width, height, pixels)
+    }
+
+    #[test]
+    fn set_labels_up_to_max_index() {
+        let (width, height, pixels) = isolated(4094, true);
+        let mut cells = vec![Cell::<u16>::default(); capacity(width, height)];
+        set(
+            &mut cells,
+            width,
+            pixels.iter().copied(),
+            Connectivity::Four,
+        );
+        let last = cells.iter().map(|&Cell(cell)| cell >> SHIFT).max();
+        assert_eq!(last, Some(16383));
+        assert_eq!(
+            rings(width, height, &pixels, Connectivity::Four).len(),
+            4096
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "16384 vertices are more than indexable 16383")]
+    fn set_panics_past_max_index() {
+        let (width, height, pixels) = isolated(4096, false);
+        let mut cells = vec![Cell::<u16>::default(); capacity(width, height)];
+        set(&mut cells, width, pixels, Connectivity::Four);
+    }
+
+    #[test]
+    #[should_panic(expected = "vertices are more than indexable 16383")]
+    fn set_packed_panics_past_max_index() {
+        let (width, height, pixels) = isolated(4096, false);
+        let words = words(width, &pixels, 3);
+        let mut cells = vec![Cell::<u16>::default(); capacity(width, height)];
+        let packed = Packed::new(width, height, &words).with_stride(3);
+        set_packed(&mut cells, packed, Connectivity::Four);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    #[should_panic(expected = "16384 vertices are more than indexable 16383")]
+    fn par_set_panics_past_max_index() {
+        let (width, height, pixels) = isolated(4096, false);
+        let mut cells = vec![Cell::<u16>::default(); capacity(width, height)];
+        par_set(&mut cells, width, &pixels, Connectivity::Four);
+    }
+
+    #[test]
+    fn try_set_fails_past_max_index() {
+        for (len, has_saddle, result) in [
+            (4094, true, Ok(())),
+            (
+                4096,
+                false,
+                Err(Error::VertexOverflow {
+                    len: 16384,
+                    max: 16383,
+                }),
+            ),
+        ] {
+            let (width, height, pixels) = isolated(len, has_saddle);
+            let mut cells = vec![Cell::<u16>::default(); capacity(width, height)];
+            let connectivity = Connectivity::Four;
+            assert_eq!(
+                try_set(&mut cells, width, height, pixels, connectivity),
+                result
+            );
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_set_matches_set() {
+        for (seed, (width, height)) in (1..).zip(
+            [0, 1, 2, 3, 17]
+                .into_iter()
+                .flat_map(|height| [0, 1, 2, 5, 70].map(|width| (width, height))),
+        ) {
+            for connectivity in [Connectivity::Four, Connectivity::Eight] {
+                let pixels = pixels(seed, width * height);
+                let mut cells = vec![Cell::<u32>::default(); capacity(width, height)];
+                set(&mut cells, width, pixels.iter().copied(), connectivity);
+                let mut par_cells = vec![Cell::<u32>::default(); capacity(width, height)];
+                par_set(&mut par_cells, width, &pixels, connectivity);
+                assert_eq!(cells, par_cells, "{width}x{height}");
+                let (_, horizontal, vertical) = get(&cells, width);
+                let indices: Vec<_> = horizontal.chain(vertical).collect();
+                let par_indices: Vec<_> = par_indices(&par_cells, width).collect();
+                assert_eq!(indices, par_indices, "{width}x{height}");
+            }
+        }
+    }
+}
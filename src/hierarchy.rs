@@ -0,0 +1,163 @@
+use crate::{
+    core::Primitive,
+    shapes::{self, Ring, Winding},
+};
+use ::alloc::vec::Vec;
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Contour<P = u16> {
+    pub ring: Ring<P>,
+    /// Index of the closest enclosing contour.
+    pub parent: Option<usize>,
+    /// Number of enclosing contours, even for outer rings and odd for holes.
+    pub depth: usize,
+    pub children: Vec<usize>,
+}
+
+impl<P> Contour<P> {
+    #[inline]
+    #[must_use]
+    pub const fn is_hole(&self) -> bool {
+        self.depth % 2 == 1
+    }
+}
+
+/// Every ring with its parent and children, like a tree of islands inside lakes inside islands.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Hierarchy<P = u16> {
+    pub vertices: Vec<[P; 2]>,
+    /// Contours in the order they were traced, so parents always go before their children.
+    pub contours: Vec<Contour<P>>,
+}
+
+impl<P: Primitive> Hierarchy<P> {
+    pub(crate) fn new(vertices: Vec<[P; 2]>, rings: Vec<Ring<P>>, winding: Winding) -> Self {
+        let parents = shapes::parents(&vertices, &rings);
+        let mut contours: Vec<Contour<P>> = Vec::with_capacity(rings.len());
+        for (index, (ring, parent)) in rings.into_iter().zip(parents).enumerate() {
+            let depth = match parent {
+                Some(parent) => {
+                    contours[parent].children.push(index);
+                    contours[parent].depth + 1
+                }
+                None => 0,
+            };
+            contours.push(Contour {
+                ring: shapes::wind(ring, winding),
+                parent,
+                depth,
+                children: Vec::new(),
+            });
+        }
+        Self { vertices, contours }
+    }
+
+    /// Indices of the contours without a parent.
+    pub fn roots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.contours
+            .iter()
+            .enumerate()
+            .filter_map(|(index, contour)| contour.parent.is_none().then_some(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            tests::{is_inside, pixels, signed_area},
+            Connectivity,
+        },
+        Algorithm, Args,
+    };
+
+    /// Checks every contour against the ones enclosing the pixels it encloses.
+    fn check(width: usize, height: usize, pixels: &[bool], connectivity: Connectivity) {
+        let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+        let hierarchy = algorithm.hierarchy(
+            Args::new(width, height, pixels.iter().copied()),
+            Winding::CounterClockwise,
+        );
+        let rings: Vec<Vec<[u16; 2]>> = hierarchy
+            .contours
+            .iter()
+            .map(|contour| {
+                contour
+                    .ring
+                    .iter()
+                    .map(|&index| hierarchy.vertices[usize::from(index)])
+                    .collect()
+            })
+            .collect();
+        let inside: Vec<Vec<bool>> = rings
+            .iter()
+            .map(|ring| {
+                (0..width * height)
+                    .map(|index| is_inside(ring, [index % width, index / width]))
+                    .collect()
+            })
+            .collect();
+        let num_inside = |index: usize| inside[index].iter().filter(|&&is_in| is_in).count();
+        for (index, contour) in hierarchy.contours.iter().enumerate() {
+            let enclosing: Vec<usize> = (0..rings.len())
+                .filter(|&other| {
+                    other != index
+                        && inside[index]
+                            .iter()
+                            .zip(&inside[other])
+                            .all(|(&is_in, &is_in_other)| !is_in || is_in_other)
+                })
+                .collect();
+            assert_eq!(contour.depth, enclosing.len(), "{connectivity:?}");
+            let parent = enclosing.into_iter().min_by_key(|&other| num_inside(other));
+            assert_eq!(contour.parent, parent, "{connectivity:?}");
+            assert_eq!(contour.is_hole(), signed_area(&rings[index]) < 0);
+            for &child in &contour.children {
+                assert_eq!(hierarchy.contours[child].parent, Some(index));
+            }
+        }
+        let num_roots = hierarchy.roots().count();
+        let num_children: usize = hierarchy
+            .contours
+            .iter()
+            .map(|contour| contour.children.len())
+            .sum();
+        assert_eq!(num_roots + num_children, hierarchy.contours.len());
+    }
+
+    #[test]
+    fn nested_islands_and_lakes() {
+        // Squares around the center, filled at even distances from it.
+        let squares: Vec<bool> = (0..81)
+            .map(|index: usize| {
+                let (x, y) = ((index % 9).abs_diff(4), (index / 9).abs_diff(4));
+                x.max(y) % 2 == 0
+            })
+            .collect();
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            check(9, 9, &squares, connectivity);
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            let hierarchy = algorithm.hierarchy(
+                Args::new(9, 9, squares.iter().copied()),
+                Winding::CounterClockwise,
+            );
+            let depths: Vec<usize> = hierarchy
+                .contours
+                .iter()
+                .map(|contour| contour.depth)
+                .collect();
+            assert_eq!(depths, [0, 1, 2, 3, 4]);
+        }
+    }
+
+    #[test]
+    fn contours_match_enclosing_rings() {
+        for (seed, (width, height)) in (1..).zip([(1, 1), (6, 4), (15, 11), (24, 20)]) {
+            let pixels = pixels(seed, width * height);
+            for connectivity in [Connectivity::Four, Connectivity::Eight] {
+                check(width, height, &pixels, connectivity);
+            }
+        }
+    }
+}
@@ -0,0 +1,566 @@
+use crate::{
+    algorithm::IntoArgs,
+    bit_grid::BitGrid,
+    core::{Connectivity, Primitive},
+};
+use ::alloc::{collections::BTreeMap, vec, vec::Vec};
+use ::core::mem;
+
+pub(crate) type Point = [isize; 2];
+
+/// Direction of an edge, with `y` pointing down.
+pub(crate) type Heading = [isize; 2];
+
+const EAST: Heading = [1, 0];
+const SOUTH: Heading = [0, 1];
+const WEST: Heading = [-1, 0];
+const NORTH: Heading = [0, -1];
+
+const fn clockwise([x, y]: Heading) -> Heading {
+    [-y, x]
+}
+
+const fn counter_clockwise([x, y]: Heading) -> Heading {
+    [y, -x]
+}
+
+pub(crate) const fn step([x, y]: Point, [dx, dy]: Heading) -> Point {
+    [x + dx, y + dy]
+}
+
+/// Headings of the edges leaving `vertex`, keeping filled pixels on their right-hand side.
+pub(crate) fn outgoing(pixel: impl Fn(Point) -> bool, [x, y]: Point) -> [Option<Heading>; 4] {
+    let (top_left, top_right) = (pixel([x - 1, y - 1]), pixel([x, y - 1]));
+    let (bottom_left, bottom_right) = (pixel([x - 1, y]), pixel([x, y]));
+    [
+        (EAST, bottom_right && !top_right),
+        (SOUTH, bottom_left && !bottom_right),
+        (WEST, top_left && !bottom_left),
+        (NORTH, top_right && !top_left),
+    ]
+    .map(|(heading, exists)| exists.then_some(heading))
+}
+
+/// Heading of the edge leaving `vertex` after arriving with `heading`, like
+/// [`crate::core::trace()`] turns.
+pub(crate) fn turn(
+    pixel: impl Fn(Point) -> bool,
+    connectivity: Connectivity,
+    vertex: Point,
+    heading: Heading,
+) -> Heading {
+    let mut outgoing = outgoing(pixel, vertex).into_iter().flatten();
+    match (outgoing.next(), outgoing.next()) {
+        (Some(to), None) => to,
+        _ if connectivity == Connectivity::Eight => counter_clockwise(heading),
+        _ => clockwise(heading),
+    }
+}
+
+const fn heading_bit(heading: Heading) -> u8 {
+    match heading {
+        EAST => 1,
+        SOUTH => 2,
+        WEST => 4,
+        _ => 8,
+    }
+}
+
+/// Vertices around a dirty rectangle of pixels, edges between two of them are exactly the ones
+/// next to a changed pixel.
+#[derive(Copy, Clone, Debug)]
+struct Bounds {
+    min: Point,
+    max: Point,
+}
+
+impl Bounds {
+    const fn contains(&self, [x, y]: Point) -> bool {
+        self.min[0] <= x && x <= self.max[0] && self.min[1] <= y && y <= self.max[1]
+    }
+
+    /// Range of `step` in `0..=len` for which `from + step * heading` is inside.
+    fn steps(&self, from: Point, heading: Heading, len: isize) -> Option<[isize; 2]> {
+        let (mut low, mut high) = (0, len);
+        for axis in 0..2 {
+            let (start, direction) = (from[axis], heading[axis]);
+            let (min, max) = (self.min[axis] - start, self.max[axis] - start);
+            if direction == 0 {
+                if min > 0 || max < 0 {
+                    return None;
+                }
+            } else {
+                let (a, b) = (min * direction, max * direction);
+                low = low.max(a.min(b));
+                high = high.min(a.max(b));
+            }
+        }
+        (low <= high).then_some([low, high])
+    }
+
+    fn index(&self, [x, y]: Point) -> usize {
+        let width = self.max[0] - self.min[0] + 1;
+        ((y - self.min[1]) * width + x - self.min[0]).cast_unsigned()
+    }
+}
+
+/// Part of an old ring outside the bounds, from the vertex it leaves them at to the one it
+/// comes back at.
+#[derive(Clone, Debug)]
+struct Chain {
+    vertices: Vec<Point>,
+    tail: Heading,
+    /// Index of the old ring among the affected ones.
+    owner: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Tracked<P> {
+    vertices: Vec<[P; 2]>,
+    min: [usize; 2],
+    max: [usize; 2],
+}
+
+/// Ids of the contours touched by an update.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Changes {
+    pub added: Vec<usize>,
+    pub removed: Vec<usize>,
+    /// Contours that kept their id, but not their vertices.
+    pub changed: Vec<usize>,
+}
+
+/// Keeps an image and its rings, so that changing a few pixels only retraces the rings around
+/// them.
+///
+/// Rings are the same as [`crate::core::trace()`] yields, as lists of vertices starting at their
+/// top-left-most vertex, and keep their id while they change. A ring split in two keeps its id
+/// for one of the parts.
+///
+/// It is not a mode of [`crate::Algorithm`], whose cells are scratch space labelled anew from
+/// every image, with vertex indices that shift whenever any pixel changes. Here the image and
+/// the rings outlive each call, and rings are followed pixel by pixel instead of labelled.
+#[derive(Clone, Debug, Default)]
+pub struct Incremental<P = u16> {
+    connectivity: Connectivity,
+    pixels: BitGrid,
+    contours: Vec<Option<Tracked<P>>>,
+    free_contours: Vec<usize>,
+    visited: Vec<u8>,
+}
+
+impl Incremental {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            connectivity: Connectivity::Four,
+            pixels: BitGrid::empty(),
+            contours: Vec::new(),
+            free_contours: Vec::new(),
+            visited: Vec::new(),
+        }
+    }
+}
+
+impl<P: Primitive> Incremental<P> {
+    /// [`Connectivity::Average`] is the same as [`Connectivity::Four`] here.
+    #[must_use]
+    pub const fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.connectivity = connectivity;
+        self
+    }
+
+    #[must_use]
+    pub const fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.pixels.width()
+    }
+
+    #[must_use]
+    pub const fn height(&self) -> usize {
+        self.pixels.height()
+    }
+
+    #[must_use]
+    pub const fn pixels(&self) -> &BitGrid {
+        &self.pixels
+    }
+
+    #[must_use]
+    pub fn contour(&self, id: usize) -> Option<&[[P; 2]]> {
+        let tracked = self.contours.get(id)?.as_ref()?;
+        Some(&tracked.vertices)
+    }
+
+    /// Every contour with its id.
+    pub fn contours(&self) -> impl Iterator<Item = (usize, &[[P; 2]])> + '_ {
+        self.contours
+            .iter()
+            .enumerate()
+            .filter_map(|(id, tracked)| {
+                let tracked = tracked.as_ref()?;
+                Some((id, tracked.vertices.as_slice()))
+            })
+    }
+
+    /// Replaces the whole image, removing every contour and adding the new ones.
+    pub fn reset(&mut self, args: impl IntoArgs) -> Changes {
+        let removed = self.contours().map(|(id, _)| id).collect();
+        self.contours.clear();
+        self.free_contours.clear();
+        self.pixels = BitGrid::from_args(args);
+        let bounds = Bounds {
+            min: [0, 0],
+            max: [self.width().cast_signed(), self.height().cast_signed()],
+        };
+        Changes {
+            removed,
+            ..self.retrace(bounds)
+        }
+    }
+
+    /// Writes the pixels of `args` at `origin` offset by the origin of `args`, ignoring the
+    /// ones outside the image, and retraces only the rings going through them.
+    pub fn update(&mut self, origin: [usize; 2], args: impl IntoArgs) -> Changes {
+        let [x, y] = [0, 1].map(|axis| origin[axis] + args.origin()[axis]);
+        let args = args.into_args();
+        let width = args.width.min(self.width().saturating_sub(x));
+        let height = args.height.min(self.height().saturating_sub(y));
+        if width == 0 || height == 0 {
+            return Changes::default();
+        }
+        let mut pixels = args.pixels.into_iter();
+        for row_index in y..y + height {
+            for (column_index, pixel) in (x..x + width).zip(pixels.by_ref()) {
+                self.pixels.set([column_index, row_index], pixel);
+            }
+            if args.width > width {
+                pixels.by_ref().take(args.width - width).for_each(drop);
+            }
+        }
+        let bounds = Bounds {
+            min: [x.cast_signed(), y.cast_signed()],
+            max: [(x + width).cast_signed(), (y + height).cast_signed()],
+        };
+        self.retrace(bounds)
+    }
+
+    fn pixel(&self, [x, y]: Point) -> bool {
+        x >= 0 && y >= 0 && self.pixels.get([x.cast_unsigned(), y.cast_unsigned()])
+    }
+
+    fn outgoing(&self, vertex: Point) -> [Option<Heading>; 4] {
+        outgoing(|point| self.pixel(point), vertex)
+    }
+
+    fn turn(&self, vertex: Point, heading: Heading) -> Heading {
+        turn(
+            |point| self.pixel(point),
+            self.connectivity,
+            vertex,
+            heading,
+        )
+    }
+
+    fn visit(&mut self, bounds: &Bounds, vertex: Point, heading: Heading) -> bool {
+        let visited = &mut self.visited[bounds.index(vertex)];
+        let bit = heading_bit(heading);
+        let is_new = *visited & bit == 0;
+        *visited |= bit;
+        is_new
+    }
+
+    /// Cuts the parts inside `bounds` out of every ring going through them, and links the
+    /// remaining chains again with the edges inside.
+    fn retrace(&mut self, bounds: Bounds) -> Changes {
+        let mut old = Vec::new();
+        let (mut chains, mut heads) = (Vec::new(), BTreeMap::new());
+        for id in 0..self.contours.len() {
+            let Some(tracked) = &self.contours[id] else {
+                continue;
+            };
+            let [min_x, min_y] = tracked.min.map(usize::cast_signed);
+            let [max_x, max_y] = tracked.max.map(usize::cast_signed);
+            if max_x < bounds.min[0]
+                || max_y < bounds.min[1]
+                || min_x > bounds.max[0]
+                || min_y > bounds.max[1]
+            {
+                continue;
+            }
+            let ring: Vec<Point> = tracked
+                .vertices
+                .iter()
+                .map(|&[x, y]| [x.into_usize().cast_signed(), y.into_usize().cast_signed()])
+                .collect();
+            if let Some(cut) = cut(&ring, &bounds, old.len()) {
+                for chain in cut {
+                    heads.insert((chain.vertices[0], heading_of(&chain)), chains.len());
+                    chains.push(Some(chain));
+                }
+                old.push((id, self.contours[id].take()));
+            }
+        }
+
+        self.visited.clear();
+        self.visited.resize(bounds.index(bounds.max) + 1, 0);
+        let mut rings = Vec::new();
+        for index in 0..chains.len() {
+            if chains[index].is_some() {
+                rings.push(self.link(&bounds, &mut chains, &heads, index));
+            }
+        }
+        for y in bounds.min[1]..=bounds.max[1] {
+            for x in bounds.min[0]..=bounds.max[0] {
+                for heading in self.outgoing([x, y]).into_iter().flatten() {
+                    if bounds.contains(step([x, y], heading))
+                        && self.visit(&bounds, [x, y], heading)
+                    {
+                        rings.push((self.walk(&bounds, [x, y], heading), Vec::new()));
+                    }
+                }
+            }
+        }
+
+        let mut changes = Changes::default();
+        let mut is_kept = vec![false; old.len()];
+        let mut added = Vec::new();
+        for (ring, owners) in rings {
+            let vertices = normalize(ring);
+            match owners.into_iter().find(|&owner| !is_kept[owner]) {
+                Some(owner) => {
+                    is_kept[owner] = true;
+                    let (id, tracked) = &old[owner];
+                    if tracked.as_ref().map(|tracked| &tracked.vertices) != Some(&vertices) {
+                        changes.changed.push(*id);
+                    }
+                    self.contours[*id] = Some(track(vertices));
+                }
+                None => added.push(vertices),
+            }
+        }
+        for vertices in added {
+            let id = if let Some(id) = self.free_contours.pop() {
+                self.contours[id] = Some(track(vertices));
+                id
+            } else {
+                self.contours.push(Some(track(vertices)));
+                self.contours.len() - 1
+            };
+            changes.added.push(id);
+        }
+        for ((id, _), is_kept) in old.into_iter().zip(is_kept) {
+            if !is_kept {
+                changes.removed.push(id);
+                self.free_contours.push(id);
+            }
+        }
+        changes
+    }
+
+    /// Follows the chain at `index` and the edges inside `bounds` until coming back to it,
+    /// returning the ring and the owners of the chains it went through.
+    fn link(
+        &mut self,
+        bounds: &Bounds,
+        chains: &mut [Option<Chain>],
+        heads: &BTreeMap<(Point, Heading), usize>,
+        index: usize,
+    ) -> (Vec<Point>, Vec<usize>) {
+        let (mut ring, mut owners) = (Vec::new(), Vec::new());
+        let mut next = Some(index);
+        while let Some(chain) = next.and_then(|next| chains[next].take()) {
+            ring.extend(chain.vertices.iter().copied());
+            owners.push(chain.owner);
+            let (mut vertex, mut heading) = (chain.vertices[chain.vertices.len() - 1], chain.tail);
+            next = loop {
+                let to = self.turn(vertex, heading);
+                let next_vertex = step(vertex, to);
+                if !bounds.contains(next_vertex) {
+                    break heads.get(&(vertex, to)).copied();
+                }
+                self.visit(bounds, vertex, to);
+                ring.push(vertex);
+                (vertex, heading) = (next_vertex, to);
+            };
+        }
+        (ring, owners)
+    }
+
+    /// Follows edges inside `bounds` from `vertex` until coming back to it.
+    fn walk(&mut self, bounds: &Bounds, start: Point, start_heading: Heading) -> Vec<Point> {
+        let mut ring = Vec::from([start]);
+        let (mut vertex, mut heading) = (step(start, start_heading), start_heading);
+        loop {
+            let to = self.turn(vertex, heading);
+            if (vertex, to) == (start, start_heading) || !bounds.contains(step(vertex, to)) {
+                break;
+            }
+            self.visit(bounds, vertex, to);
+            ring.push(vertex);
+            (vertex, heading) = (step(vertex, to), to);
+        }
+        ring
+    }
+}
+
+fn heading_of(chain: &Chain) -> Heading {
+    let ([from_x, from_y], [to_x, to_y]) = (chain.vertices[0], chain.vertices[1]);
+    [(to_x - from_x).signum(), (to_y - from_y).signum()]
+}
+
+/// Splits `ring` into the chains outside `bounds`, or returns `None` if it does not touch them.
+fn cut(ring: &[Point], bounds: &Bounds, owner: usize) -> Option<Vec<Chain>> {
+    enum Mark {
+        Corner(Point),
+        Enter(Point, Heading),
+        Exit(Point),
+    }
+
+    let (mut marks, mut is_touched) = (Vec::new(), false);
+    for (index, &from) in ring.iter().enumerate() {
+        let to = ring[(index + 1) % ring.len()];
+        let heading = [(to[0] - from[0]).signum(), (to[1] - from[1]).signum()];
+        let len = (to[0] - from[0]).abs() + (to[1] - from[1]).abs();
+        let point = |steps: isize| [from[0] + steps * heading[0], from[1] + steps * heading[1]];
+        match bounds.steps(from, heading, len) {
+            None => marks.push(Mark::Corner(from)),
+            Some([low, high]) => {
+                is_touched = true;
+                if low > 0 {
+                    marks.push(Mark::Corner(from));
+                    marks.push(Mark::Enter(point(low), heading));
+                }
+                if high < len {
+                    marks.push(Mark::Exit(point(high)));
+                }
+            }
+        }
+    }
+    if !is_touched {
+        return None;
+    }
+
+    let start = marks
+        .iter()
+        .position(|mark| matches!(mark, Mark::Exit(_)))
+        .unwrap_or(marks.len());
+    marks.rotate_left(start);
+    let (mut chains, mut vertices) = (Vec::new(), Vec::new());
+    for mark in marks {
+        match mark {
+            Mark::Exit(point) => vertices = Vec::from([point]),
+            Mark::Corner(point) => vertices.push(point),
+            Mark::Enter(point, tail) => {
+                vertices.push(point);
+                chains.push(Chain {
+                    vertices: mem::take(&mut vertices),
+                    tail,
+                    owner,
+                });
+            }
+        }
+    }
+    Some(chains)
+}
+
+/// Drops repeated and straight vertices and starts at the top-left-most one.
+pub(crate) fn normalize<P: Primitive>(mut ring: Vec<Point>) -> Vec<[P; 2]> {
+    ring.dedup();
+    while ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    let len = ring.len();
+    let direction =
+        |from: Point, to: Point| [(to[0] - from[0]).signum(), (to[1] - from[1]).signum()];
+    let mut corners: Vec<Point> = (0..len)
+        .filter(|&index| {
+            let (previous, vertex, next) = (
+                ring[(index + len - 1) % len],
+                ring[index],
+                ring[(index + 1) % len],
+            );
+            direction(previous, vertex) != direction(vertex, next)
+        })
+        .map(|index| ring[index])
+        .collect();
+    let start = (0..corners.len())
+        .min_by_key(|&index| {
+            let [x, y] = corners[index];
+            (y, x)
+        })
+        .unwrap_or(0);
+    corners.rotate_left(start);
+    corners
+        .into_iter()
+        .map(|[x, y]| {
+            [
+                P::from_usize(x.cast_unsigned()),
+                P::from_usize(y.cast_unsigned()),
+            ]
+        })
+        .collect()
+}
+
+fn track<P: Primitive>(vertices: Vec<[P; 2]>) -> Tracked<P> {
+    let (mut min, mut max) = ([usize::MAX; 2], [0; 2]);
+    for &[x, y] in &vertices {
+        let [x, y] = [x.into_usize(), y.into_usize()];
+        min = [min[0].min(x), min[1].min(y)];
+        max = [max[0].max(x), max[1].max(y)];
+    }
+    Tracked { vertices, min, max }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::tests::{canonical, pixels, rings},
+        Args, Space,
+    };
+
+    #[test]
+    fn updates_match_fresh_trace() {
+        let (width, height) = (24, 18);
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let mut incremental = Incremental::new().with_connectivity(connectivity);
+            incremental.reset(Args::new(width, height, pixels(1, width * height)));
+            for seed in 2..40 {
+                let size = [seed as usize % 7 + 1, seed as usize % 5 + 1];
+                let origin = [seed as usize * 5 % width, seed as usize * 3 % height];
+                let patch = pixels(seed, size[0] * size[1]);
+                incremental.update(origin, Args::new(size[0], size[1], patch));
+                let contours = incremental.contours().map(|(_, ring)| ring.to_vec());
+                let pixels: Vec<bool> = incremental.pixels().clone().into_iter().collect();
+                assert_eq!(
+                    canonical(contours),
+                    rings(width, height, &pixels, connectivity),
+                    "update {seed} {connectivity:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn update_offsets_by_args_origin() {
+        let (width, height) = (24, 18);
+        let patch = pixels(3, 10 * 8);
+        let mut expected = Incremental::new();
+        expected.reset(Args::new(width, height, pixels(2, width * height)));
+        let mut incremental = expected.clone();
+        let copied: Vec<bool> = (2..2 + 5)
+            .flat_map(|y| patch[y * 10 + 3..][..6].iter().copied())
+            .collect();
+        let changes = expected.update([7, 6], Args::new(6, 5, copied));
+        let roi = Args::new(10, 8, patch.iter().copied()).roi([3, 2], [6, 5]);
+        let roi = roi.with_space(Space::Image);
+        assert_eq!(incremental.update([4, 4], roi), changes);
+        assert_eq!(incremental.pixels(), expected.pixels());
+        assert!(incremental.contours().eq(expected.contours()));
+    }
+}
@@ -0,0 +1,412 @@
+use crate::{
+    core::Primitive,
+    shapes::{self, Layered, Ring, Shape, Shapes, Traced, Winding},
+};
+use ::alloc::{vec, vec::Vec};
+use ::core::mem;
+
+/// Point where the contour crosses the line between the centers of the pixels on both sides
+/// of a boundary segment, or the middle of the segment at the image border, exact in [`f32`]
+/// for images up to `2^23` pixels wide.
+#[allow(clippy::cast_precision_loss)]
+fn crossing(
+    [x, y]: [isize; 2],
+    [dx, dy]: [isize; 2],
+    [width, height]: [usize; 2],
+    samples: &[f32],
+    level: f32,
+) -> [f32; 2] {
+    let sample = |x: isize, y: isize| {
+        let [x, y] = [usize::try_from(x).ok()?, usize::try_from(y).ok()?];
+        (x < width && y < height).then(|| samples[y * width + x])
+    };
+    let (middle, normal) = ([2 * x + dx, 2 * y + dy], [-dy, dx]);
+    let inside = sample(
+        (middle[0] + normal[0]).div_euclid(2),
+        (middle[1] + normal[1]).div_euclid(2),
+    );
+    let outside = sample(
+        (middle[0] - normal[0]).div_euclid(2),
+        (middle[1] - normal[1]).div_euclid(2),
+    );
+    let offset = match (inside, outside) {
+        (Some(inside), Some(outside)) => 0.5 - (level - inside) / (outside - inside),
+        _ => 0.0,
+    };
+    [
+        middle[0] as f32 * 0.5 + normal[0] as f32 * offset,
+        middle[1] as f32 * 0.5 + normal[1] as f32 * offset,
+    ]
+}
+
+/// Puts a vertex on every boundary segment of a traced `ring`, where the field crosses `level`.
+fn interpolate_ring<P: Primitive>(
+    vertices: &[[P; 2]],
+    ring: &[P],
+    size: [usize; 2],
+    samples: &[f32],
+    level: f32,
+    iso_vertices: &mut Vec<[f32; 2]>,
+) -> Ring<P> {
+    let mut iso_ring = Vec::new();
+    for (&from, &to) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        let coordinates =
+            |index: P| vertices[index.into_usize()].map(|value| value.into_usize().cast_signed());
+        let (mut position, end) = (coordinates(from), coordinates(to));
+        let step = [
+            (end[0] - position[0]).signum(),
+            (end[1] - position[1]).signum(),
+        ];
+        while position != end {
+            iso_ring.push(P::from_usize(iso_vertices.len()));
+            iso_vertices.push(crossing(position, step, size, samples, level));
+            position = [position[0] + step[0], position[1] + step[1]];
+        }
+    }
+    iso_ring.into()
+}
+
+/// Interpolates traced `rings` at the levels of their layers, each of them once.
+///
+/// # Panics
+/// If there are more boundary segments than `P` can index
+fn interpolate<P: Primitive>(
+    rings: &[Layered<P>],
+    levels: &[f32],
+    size: [usize; 2],
+    samples: &[f32],
+    vertices: &mut Vec<[f32; 2]>,
+) -> Vec<Ring<P>> {
+    let iso_rings = rings
+        .iter()
+        .map(|layered| {
+            let level = levels[layered.layer.cast_unsigned()];
+            interpolate_ring(
+                layered.vertices,
+                layered.ring,
+                size,
+                samples,
+                level,
+                vertices,
+            )
+        })
+        .collect();
+    assert!(
+        vertices.len().saturating_sub(1) <= P::MAX.into_usize(),
+        "too many boundary segments to index"
+    );
+    iso_rings
+}
+
+/// Makes shapes of interpolated rings grouped into outer rings and holes, reversing the ones
+/// traced the other way around.
+fn shapes<'a, P: Clone>(
+    groups: Vec<(usize, Vec<usize>)>,
+    iso_rings: &'a [Ring<P>],
+    is_traced_outer: &'a [bool],
+    winding: Winding,
+) -> impl Iterator<Item = Shape<P>> + 'a {
+    let ring = move |index: usize, is_outer: bool| {
+        let iso_ring = iso_rings[index].clone();
+        let iso_ring = if is_outer == is_traced_outer[index] {
+            iso_ring
+        } else {
+            shapes::reverse(iso_ring)
+        };
+        shapes::wind(iso_ring, winding)
+    };
+    groups.into_iter().map(move |(outer, holes)| Shape {
+        outer: ring(outer, true),
+        holes: holes.into_iter().map(|hole| ring(hole, false)).collect(),
+    })
+}
+
+/// Contours of a scalar field at several levels and the filled bands between them.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Isobands<P = u16> {
+    pub vertices: Vec<[f32; 2]>,
+    /// Shapes at or above each level, tagged with the index of the level.
+    pub isolines: Vec<(usize, Shape<P>)>,
+    /// Shapes between each pair of adjacent levels, tagged with the index of the lower level.
+    pub bands: Vec<(usize, Shape<P>)>,
+}
+
+fn layered<P>(traced: &Traced<P>, layer: isize) -> impl Iterator<Item = Layered<'_, P>> {
+    traced.1.iter().map(move |ring| Layered {
+        vertices: &traced.0,
+        ring,
+        layer,
+    })
+}
+
+/// Interpolates rings traced at `level`.
+pub(crate) fn iso_shapes<P: Primitive>(
+    traced: &Traced<P>,
+    level: f32,
+    size: [usize; 2],
+    samples: &[f32],
+    winding: Winding,
+) -> Shapes<f32, P> {
+    let rings: Vec<_> = layered(traced, 0).collect();
+    let mut vertices = Vec::new();
+    let iso_rings = interpolate(&rings, &[level], size, samples, &mut vertices);
+    let is_outer: Vec<bool> = rings
+        .iter()
+        .map(|layered| shapes::is_outer(layered.vertices, layered.ring))
+        .collect();
+    let groups = shapes::group(&is_outer, &shapes::nest(&rings));
+    let shapes = shapes(groups, &iso_rings, &is_outer, winding).collect();
+    Shapes { vertices, shapes }
+}
+
+/// Closest ring enclosing each ring at the level below its own, at its own level and at the
+/// level above, with its depth, found by walking the nesting of the rings of every level once.
+fn enclosing(
+    layers: &[usize],
+    parents: &[Option<usize>],
+    num_levels: usize,
+) -> Vec<[Option<(usize, usize)>; 3]> {
+    let mut children = vec![Vec::new(); parents.len()];
+    let mut stack = Vec::new();
+    for (index, &parent) in parents.iter().enumerate().rev() {
+        match parent {
+            Some(parent) => children[parent].push(index),
+            None => stack.push((index, 0, None)),
+        }
+    }
+    // Closest enclosing ring of the ring being visited at every level, shifted by one so that
+    // the levels around the lowest and the highest ones are there too.
+    let mut closest = vec![None; num_levels + 2];
+    let mut enclosing = vec![[None; 3]; parents.len()];
+    while let Some((index, depth, maybe_left)) = stack.pop() {
+        let layer = layers[index] + 1;
+        if let Some(left) = maybe_left {
+            closest[layer] = left;
+            continue;
+        }
+        enclosing[index] = [closest[layer - 1], closest[layer], closest[layer + 1]];
+        stack.push((index, depth, Some(closest[layer])));
+        closest[layer] = Some((depth, index));
+        let children = mem::take(&mut children[index]);
+        stack.extend(
+            children
+                .into_iter()
+                .rev()
+                .map(|child| (child, depth + 1, None)),
+        );
+    }
+    enclosing
+}
+
+/// Groups `members` of one or two levels, sorted like every ring, into outer rings and holes,
+/// with `parent` telling the closest enclosing member.
+fn group(
+    members: &[usize],
+    is_outer: impl Fn(usize) -> bool,
+    parent: impl Fn(usize) -> Option<usize>,
+    positions: &mut [usize],
+) -> Vec<(usize, Vec<usize>)> {
+    for (position, &index) in members.iter().enumerate() {
+        positions[index] = position;
+    }
+    let is_outer: Vec<bool> = members.iter().map(|&index| is_outer(index)).collect();
+    let parents: Vec<_> = members
+        .iter()
+        .map(|&index| parent(index).map(|parent| positions[parent]))
+        .collect();
+    let mut groups = shapes::group(&is_outer, &parents);
+    for (outer, holes) in &mut groups {
+        *outer = members[*outer];
+        for hole in holes {
+            *hole = members[*hole];
+        }
+    }
+    groups
+}
+
+/// Interpolates rings traced at every one of `levels`, nested together once, and subtracts the
+/// rings of each level from the ones of the previous level for bands.
+pub(crate) fn isobands<P: Primitive>(
+    traced: &[Traced<P>],
+    levels: &[f32],
+    size: [usize; 2],
+    samples: &[f32],
+    winding: Winding,
+) -> Isobands<P> {
+    let mut rings: Vec<_> = traced
+        .iter()
+        .zip(0..)
+        .flat_map(|(traced, layer)| layered(traced, layer))
+        .collect();
+    rings.sort_by_key(Layered::order_key);
+    let mut isobands = Isobands::default();
+    let iso_rings = interpolate(&rings, levels, size, samples, &mut isobands.vertices);
+    let layers: Vec<usize> = rings
+        .iter()
+        .map(|layered| layered.layer.cast_unsigned())
+        .collect();
+    let is_outer: Vec<bool> = rings
+        .iter()
+        .map(|layered| shapes::is_outer(layered.vertices, layered.ring))
+        .collect();
+    let enclosing = enclosing(&layers, &shapes::nest(&rings), levels.len());
+    let mut members = vec![Vec::new(); levels.len()];
+    for (index, &layer) in layers.iter().enumerate() {
+        members[layer].push(index);
+    }
+    let mut positions = vec![0; rings.len()];
+    for (level, members) in members.iter().enumerate() {
+        let groups = group(
+            members,
+            |index| is_outer[index],
+            |index| enclosing[index][1].map(|(_, parent)| parent),
+            &mut positions,
+        );
+        isobands
+            .isolines
+            .extend(shapes(groups, &iso_rings, &is_outer, winding).map(|shape| (level, shape)));
+    }
+    for (level, pair) in members.windows(2).enumerate() {
+        let mut band_members = Vec::with_capacity(pair[0].len() + pair[1].len());
+        band_members.extend(pair.iter().flatten());
+        band_members.sort_unstable();
+        // The upper level is the lower one of a ring's band, or the other way around.
+        let groups = group(
+            &band_members,
+            |index| is_outer[index] == (layers[index] == level),
+            |index| {
+                let [below, same, above] = enclosing[index];
+                let other = if layers[index] == level { above } else { below };
+                same.max(other).map(|(_, parent)| parent)
+            },
+            &mut positions,
+        );
+        isobands
+            .bands
+            .extend(shapes(groups, &iso_rings, &is_outer, winding).map(|shape| (level, shape)));
+    }
+    isobands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{tests::canonical, Connectivity},
+        Algorithm, Args,
+    };
+    use ::core::iter;
+
+    /// Rings of `shapes` as bits of their vertices, made [`canonical()`].
+    fn rings<'a>(
+        vertices: &[[f32; 2]],
+        shapes: impl IntoIterator<Item = &'a Shape<u16>>,
+    ) -> Vec<Vec<[u32; 2]>> {
+        canonical(shapes.into_iter().flat_map(|shape| {
+            iter::once(&shape.outer).chain(&shape.holes).map(|ring| {
+                ring.iter()
+                    .map(|&index| vertices[usize::from(index)].map(f32::to_bits))
+                    .collect()
+            })
+        }))
+    }
+
+    #[test]
+    fn isolines_match_iso_shapes() {
+        let (width, height) = (23, 17);
+        let samples: Vec<f32> = crate::core::tests::pixels(5, width * height * 3)
+            .chunks(3)
+            .map(|bits| {
+                bits.iter()
+                    .fold(0.0, |sum, &bit| sum * 2.0 + f32::from(u8::from(bit)))
+            })
+            .collect();
+        let levels = [0.5, 2.5, 2.5, 4.0, 6.5];
+        for connectivity in [
+            Connectivity::Four,
+            Connectivity::Eight,
+            Connectivity::Average,
+        ] {
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            let args = Args::new(width, height, &samples[..]);
+            let isobands = algorithm.isobands(args, &levels, Winding::CounterClockwise);
+            for (index, &level) in levels.iter().enumerate() {
+                let isolines = isobands
+                    .isolines
+                    .iter()
+                    .filter(|&&(level_index, _)| level_index == index)
+                    .map(|(_, shape)| shape);
+                let iso_shapes = algorithm.iso_shapes(args, level, Winding::CounterClockwise);
+                assert_eq!(
+                    rings(&isobands.vertices, isolines),
+                    rings(&iso_shapes.vertices, &iso_shapes.shapes),
+                    "{connectivity:?} {level}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    // Vertices on lines through pixel centers or on the border have exact coordinates.
+    #[allow(clippy::float_cmp)]
+    fn iso_vertices_cross_level_inside_image() {
+        let (width, height, level) = (21_u16, 16_u16, 3.5);
+        let [max_x, max_y] = [width, height].map(f32::from);
+        let (width, height) = (usize::from(width), usize::from(height));
+        let samples: Vec<f32> = crate::core::tests::pixels(7, width * height * 3)
+            .chunks(3)
+            .map(|bits| {
+                bits.iter()
+                    .fold(0.0, |sum, &bit| sum * 2.0 + f32::from(u8::from(bit)))
+            })
+            .collect();
+        let sample = |x: f32, y: f32| {
+            // Centers of pixels are at non-negative half coordinates.
+            #[allow(clippy::cast_sign_loss)]
+            let [x, y] = [x, y].map(|value| value as usize);
+            samples[y * width + x]
+        };
+        for connectivity in [
+            Connectivity::Four,
+            Connectivity::Eight,
+            Connectivity::Average,
+        ] {
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            let args = Args::new(width, height, &samples[..]);
+            let shapes = algorithm.iso_shapes(args, level, Winding::CounterClockwise);
+            for &[x, y] in &shapes.vertices {
+                assert!((0.0..=max_x).contains(&x) && (0.0..=max_y).contains(&y));
+                // Vertices away from the border are on the line between the centers of two
+                // neighbouring pixels, where the samples interpolate to the level.
+                let (a, b, t) = if x.fract() == 0.5 && y > 0.0 && y < max_y {
+                    let top = (y - 0.5).floor() + 0.5;
+                    (sample(x, top), sample(x, top + 1.0), y - top)
+                } else if y.fract() == 0.5 && x > 0.0 && x < max_x {
+                    let left = (x - 0.5).floor() + 0.5;
+                    (sample(left, y), sample(left + 1.0, y), x - left)
+                } else {
+                    assert!(x == 0.0 || y == 0.0 || x == max_x || y == max_y);
+                    continue;
+                };
+                assert!((a + (b - a) * t - level).abs() < 1e-5, "{x},{y} {a} {b}");
+            }
+            for shape in &shapes.shapes {
+                let twice_area = |ring: &Ring| -> f32 {
+                    ring.iter()
+                        .zip(ring.iter().cycle().skip(1))
+                        .map(|(&from, &to)| {
+                            let ([x0, y0], [x1, y1]) = (
+                                shapes.vertices[usize::from(from)],
+                                shapes.vertices[usize::from(to)],
+                            );
+                            x0 * y1 - x1 * y0
+                        })
+                        .sum()
+                };
+                // Counter-clockwise with `y` up is clockwise on screen, a positive area.
+                assert!(twice_area(&shape.outer) > 0.0, "{connectivity:?}");
+                assert!(shape.holes.iter().all(|hole| twice_area(hole) < 0.0));
+            }
+        }
+    }
+}
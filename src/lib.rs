@@ -9,7 +9,78 @@ pub mod core;
 
 #[cfg(feature = "alloc")]
 mod algorithm;
+#[cfg(feature = "alloc")]
+mod bit_grid;
+#[cfg(feature = "alloc")]
+mod boundaries;
+#[cfg(feature = "alloc")]
+mod chamfer;
+#[cfg(feature = "alloc")]
+mod components;
+#[cfg(feature = "alloc")]
+mod convex;
+#[cfg(feature = "alloc")]
+mod hierarchy;
+#[cfg(feature = "alloc")]
+mod incremental;
+#[cfg(feature = "alloc")]
+mod iso;
+#[cfg(feature = "alloc")]
+mod measure;
+#[cfg(feature = "alloc")]
+mod morphology;
+#[cfg(feature = "alloc")]
+mod rectangles;
+#[cfg(feature = "alloc")]
+mod shapes;
+#[cfg(feature = "alloc")]
+mod simplify;
+#[cfg(feature = "alloc")]
+mod stream;
+#[cfg(feature = "alloc")]
+mod tiles;
+#[cfg(feature = "alloc")]
+mod triangulate;
 
 #[cfg(feature = "alloc")]
 #[cfg_attr(doc, doc(cfg(feature = "alloc")))]
-pub use algorithm::{Algorithm, Args, IntoArgs};
+pub use algorithm::{Algorithm, Args, IntoArgs, Roi, Space, Window};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use bit_grid::{BitGrid, GridPixels};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use boundaries::{Boundaries, Edge};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use components::{Component, Components};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use hierarchy::{Contour, Hierarchy};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use incremental::{Changes, Incremental};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use iso::Isobands;
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use measure::Moments;
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use morphology::{Element, Morphology, Operation};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use rectangles::{Decomposition, Rectangle, Rectangles};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use shapes::{Ring, Shape, Shapes, Winding};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use simplify::{Budget, Reduction};
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use stream::Stream;
+#[cfg(feature = "alloc")]
+#[cfg_attr(doc, doc(cfg(feature = "alloc")))]
+pub use tiles::{Tile, Tiles};
@@ -0,0 +1,242 @@
+use crate::{
+    core::Primitive,
+    shapes::{Ring, Shape},
+};
+use ::core::{
+    iter::{self, Sum},
+    ops::{Add, AddAssign, Neg},
+};
+
+/// Integrals over the area of rings of lattice points, scaled by the smallest factors keeping
+/// them integers, so they are exact for coordinates below `2^30`.
+///
+/// Rings turning counter-clockwise in a frame where `y` points up count positively.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Moments {
+    /// Twice the area, halved by [`Self::area()`].
+    pub twice_area: i128,
+    /// Six times the integrals of `x` and `y`, the centroid is `first / (3 * twice_area)`.
+    pub first: [i128; 2],
+    /// Twelve times the integrals of `x * x` and `y * y`, and twenty-four times the one of
+    /// `x * y`.
+    pub second: [i128; 3],
+}
+
+// The exact moments are only rounded once converted.
+#[allow(clippy::cast_precision_loss)]
+impl Moments {
+    #[must_use]
+    pub fn area(&self) -> f64 {
+        self.twice_area as f64 / 2.0
+    }
+
+    /// [`None`] for rings without area.
+    #[must_use]
+    pub fn centroid(&self) -> Option<[f64; 2]> {
+        let area = 3.0 * self.twice_area as f64;
+        (self.twice_area != 0).then(|| self.first.map(|first| first as f64 / area))
+    }
+
+    /// Second moments of area about the centroid, of `x * x`, `y * y` and `x * y`, the first
+    /// two adding up to the moment of inertia of a body with unit density.
+    #[must_use]
+    pub fn central(&self) -> Option<[f64; 3]> {
+        let [x, y] = self.centroid()?;
+        let [first_x, first_y] = self.first.map(|first| first as f64 / 6.0);
+        let [xx, yy, xy] = self.second;
+        Some([
+            xx as f64 / 12.0 - first_x * x,
+            yy as f64 / 12.0 - first_y * y,
+            xy as f64 / 24.0 - first_x * y,
+        ])
+    }
+}
+
+impl Add for Moments {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for Moments {
+    fn add_assign(&mut self, rhs: Self) {
+        self.twice_area = self.twice_area.wrapping_add(rhs.twice_area);
+        for (moment, rhs) in self.first.iter_mut().zip(rhs.first) {
+            *moment = moment.wrapping_add(rhs);
+        }
+        for (moment, rhs) in self.second.iter_mut().zip(rhs.second) {
+            *moment = moment.wrapping_add(rhs);
+        }
+    }
+}
+
+impl Neg for Moments {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            twice_area: self.twice_area.wrapping_neg(),
+            first: self.first.map(i128::wrapping_neg),
+            second: self.second.map(i128::wrapping_neg),
+        }
+    }
+}
+
+impl Sum for Moments {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl<P: Primitive> Ring<P> {
+    /// Sums over the edges, wrapping so that only the totals need to fit.
+    #[must_use]
+    pub fn moments(&self, vertices: &[[P; 2]]) -> Moments {
+        let vertex = |index: P| vertices[index.into_usize()].map(|c| c.into_usize() as i128);
+        let mut moments = Moments::default();
+        for (&from, &to) in self.iter().zip(self.iter().cycle().skip(1)) {
+            let ([x0, y0], [x1, y1]) = (vertex(from), vertex(to));
+            let (mul, add) = (i128::wrapping_mul, i128::wrapping_add);
+            let cross = mul(x0, y1).wrapping_sub(mul(x1, y0));
+            let square = |a, b| add(add(mul(a, a), mul(a, b)), mul(b, b));
+            let xy = add(
+                add(mul(x0, y1), mul(x1, y0)),
+                mul(2, add(mul(x0, y0), mul(x1, y1))),
+            );
+            moments += Moments {
+                twice_area: cross,
+                first: [mul(add(x0, x1), cross), mul(add(y0, y1), cross)],
+                second: [
+                    mul(square(x0, x1), cross),
+                    mul(square(y0, y1), cross),
+                    mul(xy, cross),
+                ],
+            };
+        }
+        moments
+    }
+
+    /// Length of the ring, exact since pixel edges are horizontal or vertical.
+    #[must_use]
+    pub fn perimeter(&self, vertices: &[[P; 2]]) -> usize {
+        let vertex = |index: P| vertices[index.into_usize()].map(Primitive::into_usize);
+        self.iter()
+            .zip(self.iter().cycle().skip(1))
+            .map(|(&from, &to)| {
+                let ([x0, y0], [x1, y1]) = (vertex(from), vertex(to));
+                x0.abs_diff(x1) + y0.abs_diff(y1)
+            })
+            .sum()
+    }
+}
+
+impl<P: Primitive> Shape<P> {
+    /// Moments of the outer ring without the holes, with a positive area whichever way the
+    /// rings turn.
+    #[must_use]
+    pub fn moments(&self, vertices: &[[P; 2]]) -> Moments {
+        let moments: Moments = iter::once(&self.outer)
+            .chain(&self.holes)
+            .map(|ring| ring.moments(vertices))
+            .sum();
+        if moments.twice_area < 0 {
+            -moments
+        } else {
+            moments
+        }
+    }
+
+    /// Length of the outer ring and of the holes.
+    #[must_use]
+    pub fn perimeter(&self, vertices: &[[P; 2]]) -> usize {
+        iter::once(&self.outer)
+            .chain(&self.holes)
+            .map(|ring| ring.perimeter(vertices))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            tests::{is_inside, pixels},
+            Connectivity,
+        },
+        Algorithm, Args, Winding,
+    };
+    use ::alloc::vec::Vec;
+
+    #[test]
+    fn moments_and_perimeter_match_pixels() {
+        let (width, height) = (19, 14);
+        for (seed, connectivity) in [(1, Connectivity::Four), (2, Connectivity::Eight)] {
+            let pixels = pixels(seed, width * height);
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            let shapes = algorithm.shapes(
+                Args::new(width, height, pixels.iter().copied()),
+                Winding::CounterClockwise,
+            );
+            let ring = |ring: &Ring| -> Vec<[u16; 2]> {
+                ring.iter()
+                    .map(|&index| shapes.vertices[usize::from(index)])
+                    .collect()
+            };
+            let is_filled = |x: Option<usize>, y: Option<usize>| match (x, y) {
+                (Some(x), Some(y)) if x < width && y < height => pixels[y * width + x],
+                _ => false,
+            };
+            let mut num_pixels = 0;
+            for shape in &shapes.shapes {
+                let (outer, holes) = (ring(&shape.outer), shape.holes.iter().map(ring));
+                let holes: Vec<_> = holes.collect();
+                let (mut expected, mut perimeter) = (Moments::default(), 0);
+                for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+                    let is_in_shape = is_inside(&outer, [x, y])
+                        && !holes.iter().any(|hole| is_inside(hole, [x, y]));
+                    if !is_in_shape {
+                        continue;
+                    }
+                    assert!(is_filled(Some(x), Some(y)));
+                    num_pixels += 1;
+                    let [x, y] = [x, y].map(|value| i128::try_from(value).unwrap());
+                    // Integrals over the unit square with its top-left corner at `[x, y]`.
+                    expected += Moments {
+                        twice_area: 2,
+                        first: [6 * x + 3, 6 * y + 3],
+                        second: [
+                            12 * x * x + 12 * x + 4,
+                            12 * y * y + 12 * y + 4,
+                            24 * x * y + 12 * x + 12 * y + 6,
+                        ],
+                    };
+                    let [x, y] = [x, y].map(|value| usize::try_from(value).unwrap());
+                    perimeter += [
+                        (x.checked_sub(1), Some(y)),
+                        (Some(x + 1), Some(y)),
+                        (Some(x), y.checked_sub(1)),
+                        (Some(x), Some(y + 1)),
+                    ]
+                    .into_iter()
+                    .filter(|&(x, y)| !is_filled(x, y))
+                    .count();
+                }
+                assert_eq!(
+                    shape.moments(&shapes.vertices),
+                    expected,
+                    "{connectivity:?}"
+                );
+                assert_eq!(
+                    shape.perimeter(&shapes.vertices),
+                    perimeter,
+                    "{connectivity:?}"
+                );
+            }
+            assert_eq!(num_pixels, pixels.iter().filter(|&&pixel| pixel).count());
+        }
+    }
+}
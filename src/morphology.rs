@@ -0,0 +1,261 @@
+use crate::{
+    algorithm::{Args, IntoArgs},
+    bit_grid::{BitGrid, GridPixels},
+    core::Packed,
+};
+use ::alloc::{vec, vec::Vec};
+use ::core::mem;
+
+/// Neighbourhood of up to `radius` pixels around each pixel, in which morphology looks for
+/// filled or empty ones.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Element {
+    Square(usize),
+    /// Pixels in the same row or column.
+    Cross(usize),
+    /// Pixels with centers at most `radius` away.
+    Disc(usize),
+}
+
+impl Element {
+    const fn radius(self) -> usize {
+        match self {
+            Self::Square(radius) | Self::Cross(radius) | Self::Disc(radius) => radius,
+        }
+    }
+
+    /// How far the element reaches to the sides in the row `dy` away from its middle.
+    const fn half_width(self, dy: usize) -> usize {
+        match self {
+            Self::Square(radius) => radius,
+            Self::Cross(radius) => {
+                if dy == 0 {
+                    radius
+                } else {
+                    0
+                }
+            }
+            Self::Disc(radius) => (radius * radius - dy * dy).isqrt(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Operation {
+    /// Fills pixels with a filled one in their neighbourhood, growing shapes and closing gaps.
+    Dilate,
+    /// Clears pixels with an empty one in their neighbourhood, shrinking shapes and removing
+    /// specks. Pixels outside the image do not count.
+    Erode,
+    /// [`Self::Erode`] then [`Self::Dilate`], removing specks smaller than the element while
+    /// keeping the rest.
+    Open,
+    /// [`Self::Dilate`] then [`Self::Erode`], closing gaps and holes smaller than the element
+    /// while keeping the rest.
+    Close,
+}
+
+impl BitGrid {
+    pub fn dilate(&mut self, element: Element) {
+        let stride = self.stride();
+        if stride == 0 {
+            return;
+        }
+        let last_mask = match self.width() % 64 {
+            0 => u64::MAX,
+            len => (1 << len) - 1,
+        };
+        let height = self.height();
+        let mut dilated = vec![0; self.words.len()];
+        // Rows spread to the sides by `half_width`.
+        let (mut spread, mut row) = (self.words.clone(), Vec::with_capacity(stride));
+        for half_width in 0..=element.radius() {
+            if half_width > 0 {
+                for words in spread.chunks_exact_mut(stride) {
+                    row.clear();
+                    row.extend_from_slice(words);
+                    for (index, word) in words.iter_mut().enumerate() {
+                        let previous = index.checked_sub(1).map_or(0, |index| row[index]);
+                        let next = row.get(index + 1).copied().unwrap_or(0);
+                        let current = row[index];
+                        *word = current
+                            | (current << 1)
+                            | (previous >> 63)
+                            | (current >> 1)
+                            | (next << 63);
+                    }
+                    words[stride - 1] &= last_mask;
+                }
+            }
+            for dy in (0..=element.radius()).filter(|&dy| element.half_width(dy) == half_width) {
+                for y in 0..height {
+                    for source in [y.checked_sub(dy), Some(y + dy)] {
+                        let Some(source) = source.filter(|&source| source < height) else {
+                            continue;
+                        };
+                        let (target, source) = (y * stride, source * stride);
+                        for index in 0..stride {
+                            dilated[target + index] |= spread[source + index];
+                        }
+                    }
+                }
+            }
+        }
+        self.words = dilated;
+    }
+
+    pub fn erode(&mut self, element: Element) {
+        let mut inverted = !mem::take(self);
+        inverted.dilate(element);
+        *self = !inverted;
+    }
+
+    pub fn apply(&mut self, operation: Operation, element: Element) {
+        match operation {
+            Operation::Dilate => self.dilate(element),
+            Operation::Erode => self.erode(element),
+            Operation::Open => {
+                self.erode(element);
+                self.dilate(element);
+            }
+            Operation::Close => {
+                self.dilate(element);
+                self.erode(element);
+            }
+        }
+    }
+}
+
+/// Pixels of `args` after morphology, collected into a [`BitGrid`] up front so they are
+/// labelled straight from its words.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Morphology {
+    grid: BitGrid,
+    origin: [usize; 2],
+}
+
+impl Morphology {
+    #[must_use]
+    pub fn new(args: impl IntoArgs, operation: Operation, element: Element) -> Self {
+        let origin = args.origin();
+        let mut grid = BitGrid::from_args(args);
+        grid.apply(operation, element);
+        Self { grid, origin }
+    }
+
+    #[must_use]
+    pub const fn grid(&self) -> &BitGrid {
+        &self.grid
+    }
+}
+
+impl IntoArgs for Morphology {
+    type Pixels = GridPixels;
+
+    fn into_args(self) -> Args<Self::Pixels> {
+        Args::new(self.grid.width(), self.grid.height(), self.grid.into_iter())
+    }
+
+    #[inline]
+    fn origin(&self) -> [usize; 2] {
+        self.origin
+    }
+
+    #[inline]
+    fn packed(&self) -> Option<Packed<'_>> {
+        Some(self.grid.packed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::tests::pixels, Algorithm, Args, Space, Winding};
+
+    /// Pixels with a filled one, or with only filled ones inside the image, within `element`.
+    fn scan(width: usize, pixels: &[bool], element: Element, is_any: bool) -> Vec<bool> {
+        let height = pixels.len() / width;
+        let radius = element.radius().cast_signed();
+        (0..pixels.len())
+            .map(|index| {
+                let (x, y) = ((index % width).cast_signed(), (index / width).cast_signed());
+                let mut neighbours = (-radius..=radius)
+                    .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+                    .filter(|&(dx, dy)| match element {
+                        Element::Square(_) => true,
+                        Element::Cross(_) => dx == 0 || dy == 0,
+                        Element::Disc(_) => dx * dx + dy * dy <= radius * radius,
+                    })
+                    .filter_map(|(dx, dy)| {
+                        let (x, y) = (usize::try_from(x + dx).ok()?, usize::try_from(y + dy).ok()?);
+                        (x < width && y < height).then(|| pixels[y * width + x])
+                    });
+                if is_any {
+                    neighbours.any(|pixel| pixel)
+                } else {
+                    neighbours.all(|pixel| pixel)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn operations_match_neighbourhood_scan() {
+        for (seed, (width, height)) in
+            (1..).zip([(1, 1), (7, 5), (63, 6), (64, 4), (65, 7), (130, 5)])
+        {
+            let pixels = pixels(seed, width * height);
+            let args = || Args::new(width, height, pixels.iter().copied());
+            for element in [
+                Element::Square(0),
+                Element::Square(2),
+                Element::Cross(1),
+                Element::Cross(3),
+                Element::Disc(2),
+                Element::Disc(3),
+            ] {
+                let dilated = scan(width, &pixels, element, true);
+                let eroded = scan(width, &pixels, element, false);
+                for (operation, expected) in [
+                    (Operation::Dilate, dilated.clone()),
+                    (Operation::Erode, eroded.clone()),
+                    (Operation::Open, scan(width, &eroded, element, true)),
+                    (Operation::Close, scan(width, &dilated, element, false)),
+                ] {
+                    let morphology = Morphology::new(args(), operation, element);
+                    let expected = BitGrid::from_args(Args::new(width, height, expected));
+                    assert_eq!(
+                        morphology.grid(),
+                        &expected,
+                        "{width}x{height} {operation:?} {element:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn morphology_is_labelled_from_packed_words() {
+        let (width, height) = (70, 40);
+        let pixels = pixels(3, width * height);
+        let args = Args::new(width, height, pixels.iter().copied());
+        let morphology = Morphology::new(
+            args.roi([5, 3], [60, 30]).with_space(Space::Image),
+            Operation::Open,
+            Element::Disc(1),
+        );
+        assert_eq!(morphology.packed(), Some(morphology.grid().packed()));
+        assert_eq!(morphology.origin(), [5, 3]);
+
+        let mut algorithm = Algorithm::new();
+        let grid = Args::new(60, 30, morphology.grid().clone());
+        let mut expected = algorithm.shapes(grid, Winding::CounterClockwise);
+        for vertex in &mut expected.vertices {
+            *vertex = [vertex[0] + 5, vertex[1] + 3];
+        }
+        assert_eq!(
+            algorithm.shapes(morphology, Winding::CounterClockwise),
+            expected
+        );
+    }
+}
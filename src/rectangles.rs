@@ -0,0 +1,401 @@
+use crate::{algorithm::IntoArgs, bit_grid::BitGrid, core::Primitive};
+use ::alloc::{collections::VecDeque, vec, vec::Vec};
+use ::core::mem;
+
+/// How [`Rectangles`] cover the filled pixels.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Decomposition {
+    /// Runs of pixels in a row grow down while the next row has the same run.
+    #[default]
+    Greedy,
+    /// Fewest rectangles, cutting along as many straight lines joining two concave corners as
+    /// possible.
+    Minimal,
+}
+
+/// Axis-aligned rectangle of filled pixels.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Rectangle<P = u16> {
+    /// Top-left corner, in the same space as the vertices of [`crate::Algorithm::search()`].
+    pub min: [P; 2],
+    pub max: [P; 2],
+}
+
+/// Rectangles covering every filled pixel once, like box colliders of a tile map.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Rectangles<P = u16> {
+    pub rectangles: Vec<Rectangle<P>>,
+}
+
+impl Rectangles {
+    #[must_use]
+    pub fn new(args: impl IntoArgs, decomposition: Decomposition) -> Self {
+        Self::from_args(args, decomposition)
+    }
+}
+
+impl<P: Primitive> Rectangles<P> {
+    /// # Panics
+    /// If corners do not fit in `P`
+    #[must_use]
+    pub fn from_args(args: impl IntoArgs, decomposition: Decomposition) -> Self {
+        let [origin_x, origin_y] = args.origin();
+        let mut grid = BitGrid::from_args(args);
+        let max = (origin_x + grid.width()).max(origin_y + grid.height());
+        assert!(max <= P::MAX.into_usize(), "corners do not fit");
+        let rectangles = match decomposition {
+            Decomposition::Greedy => greedy(&grid),
+            Decomposition::Minimal => minimal(&mut grid),
+        };
+        let rectangles = rectangles
+            .into_iter()
+            .map(|[min_x, min_y, max_x, max_y]| Rectangle {
+                min: [
+                    P::from_usize(origin_x + min_x),
+                    P::from_usize(origin_y + min_y),
+                ],
+                max: [
+                    P::from_usize(origin_x + max_x),
+                    P::from_usize(origin_y + max_y),
+                ],
+            })
+            .collect();
+        Self { rectangles }
+    }
+}
+
+/// Rectangles as `[min_x, min_y, max_x, max_y]`.
+fn greedy(grid: &BitGrid) -> Vec<[usize; 4]> {
+    let mut rectangles = Vec::new();
+    // Runs of the current row with the row their rectangle started at, and of the previous one.
+    let (mut open, mut previous): (Vec<([usize; 2], usize)>, _) = (Vec::new(), Vec::new());
+    for y in 0..=grid.height() {
+        mem::swap(&mut open, &mut previous);
+        let mut previous = previous.drain(..).peekable();
+        let mut x = 0;
+        while y < grid.height() && x < grid.width() {
+            if !grid.get([x, y]) {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while grid.get([x, y]) {
+                x += 1;
+            }
+            let run = [start, x];
+            while let Some(([start, end], top)) = previous.next_if(|&(other, _)| other < run) {
+                rectangles.push([start, top, end, y]);
+            }
+            let top = previous
+                .next_if(|&(other, _)| other == run)
+                .map_or(y, |(_, top)| top);
+            open.push((run, top));
+        }
+        for ([start, end], top) in previous {
+            rectangles.push([start, top, end, y]);
+        }
+    }
+    rectangles
+}
+
+/// Chord between two concave corners along a line of the grid, from `start` to `end` on
+/// `line`.
+#[derive(Copy, Clone, Debug)]
+struct Chord {
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Pixels around a point, top-left, top-right, bottom-left and bottom-right.
+fn around(grid: &BitGrid, x: usize, y: usize) -> [bool; 4] {
+    [
+        grid.get([x.wrapping_sub(1), y.wrapping_sub(1)]),
+        grid.get([x, y.wrapping_sub(1)]),
+        grid.get([x.wrapping_sub(1), y]),
+        grid.get([x, y]),
+    ]
+}
+
+fn is_concave(grid: &BitGrid, x: usize, y: usize) -> bool {
+    around(grid, x, y).iter().filter(|&&pixel| pixel).count() == 3
+}
+
+fn is_inside(grid: &BitGrid, x: usize, y: usize) -> bool {
+    around(grid, x, y) == [true; 4]
+}
+
+/// Cuts along a largest set of chords not touching each other, then from every concave corner
+/// left, and reads the rectangles between the cuts.
+fn minimal(grid: &mut BitGrid) -> Vec<[usize; 4]> {
+    let (width, height) = (grid.width(), grid.height());
+    let concave: Vec<[usize; 2]> = (1..height)
+        .flat_map(|y| (1..width).map(move |x| [x, y]))
+        .filter(|&[x, y]| is_concave(grid, x, y))
+        .collect();
+    let (horizontal, vertical) = chords(grid, &concave);
+    let (is_horizontal_kept, is_vertical_kept) = independent_chords(&horizontal, &vertical, width);
+    // Cuts from every point to the right and down.
+    let stride = width + 1;
+    let (mut right, mut down) = (
+        vec![false; stride * (height + 1)],
+        vec![false; stride * (height + 1)],
+    );
+    for (chord, _) in horizontal
+        .iter()
+        .zip(is_horizontal_kept)
+        .filter(|&(_, is_kept)| is_kept)
+    {
+        right[chord.line * stride + chord.start..chord.line * stride + chord.end].fill(true);
+    }
+    for (chord, _) in vertical
+        .iter()
+        .zip(is_vertical_kept)
+        .filter(|&(_, is_kept)| is_kept)
+    {
+        for y in chord.start..chord.end {
+            down[y * stride + chord.line] = true;
+        }
+    }
+    let is_cut = |right: &[bool], down: &[bool], x: usize, y: usize| {
+        let point = y * stride + x;
+        right[point]
+            || down[point]
+            || (x > 0 && right[point - 1])
+            || (y > 0 && down[point - stride])
+    };
+    // Every concave corner without a cut yet is cut sideways into the shape until the cut
+    // meets the outline or another cut.
+    for &[x, y] in &concave {
+        if is_cut(&right, &down, x, y) {
+            continue;
+        }
+        let [top_left, _, bottom_left, _] = around(grid, x, y);
+        let mut x = x;
+        loop {
+            let next = if top_left && bottom_left {
+                x - 1
+            } else {
+                x + 1
+            };
+            let is_blocked = is_cut(&right, &down, next, y);
+            right[y * stride + x.min(next)] = true;
+            x = next;
+            if is_blocked || !is_inside(grid, x, y) {
+                break;
+            }
+        }
+    }
+    rectangles_between(grid, &right, &down)
+}
+
+/// Chords going right and down from every concave corner, through points inside the shape.
+fn chords(grid: &BitGrid, concave: &[[usize; 2]]) -> (Vec<Chord>, Vec<Chord>) {
+    let (mut horizontal, mut vertical) = (Vec::new(), Vec::new());
+    for &[x, y] in concave {
+        let [top_left, top_right, bottom_left, _] = around(grid, x, y);
+        if top_right && !(top_left && bottom_left) {
+            let mut end = x + 1;
+            while is_inside(grid, end, y) {
+                end += 1;
+            }
+            if is_concave(grid, end, y) {
+                horizontal.push(Chord {
+                    line: y,
+                    start: x,
+                    end,
+                });
+            }
+        }
+        if bottom_left && !(top_left && top_right) {
+            let mut end = y + 1;
+            while is_inside(grid, x, end) {
+                end += 1;
+            }
+            if is_concave(grid, x, end) {
+                vertical.push(Chord {
+                    line: x,
+                    start: y,
+                    end,
+                });
+            }
+        }
+    }
+    (horizontal, vertical)
+}
+
+/// Whether every horizontal and vertical chord is in a largest set of chords not touching each
+/// other, the complement of a smallest vertex cover of the graph of crossing chords.
+fn independent_chords(
+    horizontal: &[Chord],
+    vertical: &[Chord],
+    width: usize,
+) -> (Vec<bool>, Vec<bool>) {
+    let mut columns = vec![Vec::new(); width + 1];
+    for (index, chord) in vertical.iter().enumerate() {
+        columns[chord.line].push(index);
+    }
+    let crossings: Vec<Vec<usize>> = horizontal
+        .iter()
+        .map(|chord| {
+            (chord.start..=chord.end)
+                .flat_map(|x| &columns[x])
+                .copied()
+                .filter(|&index| {
+                    (vertical[index].start..=vertical[index].end).contains(&chord.line)
+                })
+                .collect()
+        })
+        .collect();
+    let (mut matches, mut matched_by) = (vec![None; horizontal.len()], vec![None; vertical.len()]);
+    let (mut from, mut visits, mut queue) = (
+        vec![0; vertical.len()],
+        vec![0; vertical.len()],
+        VecDeque::new(),
+    );
+    for (root, visit) in (0..horizontal.len()).zip(1..) {
+        queue.clear();
+        queue.push_back(root);
+        'search: while let Some(chord) = queue.pop_front() {
+            for &crossing in &crossings[chord] {
+                if visits[crossing] == visit {
+                    continue;
+                }
+                (visits[crossing], from[crossing]) = (visit, chord);
+                let Some(next) = matched_by[crossing] else {
+                    let mut crossing = Some(crossing);
+                    while let Some(current) = crossing {
+                        let chord = from[current];
+                        crossing = matches[chord];
+                        (matches[chord], matched_by[current]) = (Some(current), Some(chord));
+                    }
+                    break 'search;
+                };
+                queue.push_back(next);
+            }
+        }
+    }
+    // Chords reachable from unmatched horizontal ones by alternating paths are kept if
+    // horizontal, the other vertical ones too.
+    let (mut is_reached, mut is_crossing_reached) =
+        (vec![false; horizontal.len()], vec![false; vertical.len()]);
+    queue.clear();
+    for (chord, matched) in matches.iter().enumerate() {
+        if matched.is_none() {
+            is_reached[chord] = true;
+            queue.push_back(chord);
+        }
+    }
+    while let Some(chord) = queue.pop_front() {
+        for &crossing in &crossings[chord] {
+            if !is_crossing_reached[crossing] {
+                is_crossing_reached[crossing] = true;
+                if let Some(next) = matched_by[crossing].filter(|&next| !is_reached[next]) {
+                    is_reached[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    for is_reached in &mut is_crossing_reached {
+        *is_reached = !*is_reached;
+    }
+    (is_reached, is_crossing_reached)
+}
+
+/// Reads the rectangles of filled pixels between the cuts going `right` and `down` from every
+/// point, clearing `grid` on the way.
+fn rectangles_between(grid: &mut BitGrid, right: &[bool], down: &[bool]) -> Vec<[usize; 4]> {
+    let (width, height) = (grid.width(), grid.height());
+    let stride = width + 1;
+    let mut rectangles = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if !grid.get([x, y]) {
+                continue;
+            }
+            let mut max_x = x + 1;
+            while grid.get([max_x, y]) && !down[y * stride + max_x] {
+                max_x += 1;
+            }
+            let mut max_y = y + 1;
+            while grid.get([x, max_y]) && !right[max_y * stride + x] {
+                max_y += 1;
+            }
+            for y in y..max_y {
+                for x in x..max_x {
+                    grid.set([x, y], false);
+                }
+            }
+            rectangles.push([x, y, max_x, max_y]);
+        }
+    }
+    rectangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::tests::pixels, Args};
+    use ::alloc::collections::BTreeMap;
+
+    /// Fewest rectangles covering the bits of `mask`, found by covering the first bit left with
+    /// every rectangle having it as top-left corner.
+    fn fewest(mask: u64, [width, height]: [usize; 2], memo: &mut BTreeMap<u64, usize>) -> usize {
+        if mask == 0 {
+            return 0;
+        }
+        if let Some(&fewest) = memo.get(&mask) {
+            return fewest;
+        }
+        let first = mask.trailing_zeros() as usize;
+        let (x, y) = (first % width, first / width);
+        // Bits from `x` to `max_x` of row `y`.
+        let run = |max_x: usize, y: usize| ((1 << max_x) - (1 << x)) << (y * width);
+        let mut best = usize::MAX;
+        let mut max_x = width;
+        for max_y in y + 1..=height {
+            while max_x > x && mask & run(max_x, max_y - 1) != run(max_x, max_y - 1) {
+                max_x -= 1;
+            }
+            for max_x in x + 1..=max_x {
+                let rectangle = (y..max_y).fold(0, |rectangle, y| rectangle | run(max_x, y));
+                best = best.min(1 + fewest(mask & !rectangle, [width, height], memo));
+            }
+        }
+        memo.insert(mask, best);
+        best
+    }
+
+    #[test]
+    fn minimal_matches_fewest() {
+        for (seed, (width, height)) in
+            (1..).zip((1..=6).flat_map(|width| (1..=6).map(move |height| (width, height))))
+        {
+            let pixels = pixels(seed, width * height);
+            let rectangles: Rectangles = Rectangles::new(
+                Args::new(width, height, pixels.iter().copied()),
+                Decomposition::Minimal,
+            );
+            let mut covered = vec![false; width * height];
+            for Rectangle { min, max } in &rectangles.rectangles {
+                for y in min[1]..max[1] {
+                    for x in min[0]..max[0] {
+                        let index = usize::from(y) * width + usize::from(x);
+                        assert!(pixels[index] && !covered[index], "{width}x{height}");
+                        covered[index] = true;
+                    }
+                }
+            }
+            assert_eq!(covered, pixels, "{width}x{height}");
+            let mask = (0..width * height)
+                .filter(|&index| pixels[index])
+                .fold(0, |mask, index| mask | 1 << index);
+            assert_eq!(
+                rectangles.rectangles.len(),
+                fewest(mask, [width, height], &mut BTreeMap::new()),
+                "{width}x{height}"
+            );
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::core::{Primitive, RingVertex};
+use ::alloc::{collections::BTreeMap, vec::Vec};
+use ::core::{iter, mem, ops::Deref};
+
+/// Direction of outer rings in a frame where `y` points up, holes always go the other way.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub enum Winding {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+/// Closed ring of indices into [`Shapes::vertices`], the last index connects to the first.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Ring<P = u16>(pub(crate) Vec<P>);
+
+impl<P> Ring<P> {
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> Vec<P> {
+        self.0
+    }
+}
+
+impl<P> Deref for Ring<P> {
+    type Target = [P];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<P> From<Vec<P>> for Ring<P> {
+    #[inline]
+    fn from(indices: Vec<P>) -> Self {
+        Self(indices)
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Shape<P = u16> {
+    pub outer: Ring<P>,
+    pub holes: Vec<Ring<P>>,
+}
+
+/// Shapes with vertices of type `T`, indexed by `P`.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Shapes<T = u16, P = u16> {
+    pub vertices: Vec<[T; 2]>,
+    pub shapes: Vec<Shape<P>>,
+}
+
+impl<T, P> Shapes<T, P> {
+    /// Every ring, outer rings followed by their holes.
+    pub fn rings(&self) -> impl Iterator<Item = &Ring<P>> + '_ {
+        self.shapes
+            .iter()
+            .flat_map(|shape| iter::once(&shape.outer).chain(&shape.holes))
+    }
+}
+
+/// Vertices and rings traced by [`crate::core::trace()`].
+pub(crate) type Traced<P> = (Vec<[P; 2]>, Vec<Ring<P>>);
+
+pub(crate) fn collect_rings<P: Primitive>(
+    ring_vertices: impl IntoIterator<Item = RingVertex<P>>,
+) -> Vec<Ring<P>> {
+    let mut rings = Vec::new();
+    for vertex in ring_vertices {
+        match vertex {
+            RingVertex::Start(index) => rings.push(Ring(Vec::from([index]))),
+            RingVertex::Next(index) => {
+                if let Some(Ring(ring)) = rings.last_mut() {
+                    ring.push(index);
+                }
+            }
+        }
+    }
+    rings
+}
+
+/// Traced rings leave their start vertex eastward when they are outer and southward otherwise.
+pub(crate) fn is_outer<P: Primitive>(vertices: &[[P; 2]], ring: &[P]) -> bool {
+    vertices[ring[0].into_usize()][1] == vertices[ring[1].into_usize()][1]
+}
+
+pub(crate) fn reverse<P>(mut ring: Ring<P>) -> Ring<P> {
+    ring.0[1..].reverse();
+    ring
+}
+
+pub(crate) fn wind<P>(ring: Ring<P>, winding: Winding) -> Ring<P> {
+    match winding {
+        Winding::CounterClockwise => ring,
+        Winding::Clockwise => reverse(ring),
+    }
+}
+
+/// Ring traced at one of several nested levels, with `layer` growing with the level.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Layered<'a, P> {
+    pub vertices: &'a [[P; 2]],
+    pub ring: &'a [P],
+    pub layer: isize,
+}
+
+impl<P: Primitive> Layered<'_, P> {
+    fn start(&self) -> [P; 2] {
+        self.vertices[self.ring[0].into_usize()]
+    }
+
+    /// Position of the edge going down from the start among edges that coincide with it, since
+    /// an interpolated contour of a higher level lies further from the outside of the level.
+    fn start_key(&self) -> (P, isize) {
+        let [x, _] = self.start();
+        if is_outer(self.vertices, self.ring) {
+            (x, self.layer)
+        } else {
+            (x, -self.layer)
+        }
+    }
+
+    /// Order of [`crate::core::trace()`], extended to rings of several levels.
+    pub fn order_key(&self) -> (P, P, isize) {
+        let ([_, y], (x, layer)) = (self.start(), self.start_key());
+        (y, x, layer)
+    }
+}
+
+/// Finds the closest ring enclosing each ring by casting a ray to the left of its start.
+///
+/// Rings have to be sorted by [`Layered::order_key()`], so every ring crossed by the ray has
+/// already got its parent.
+pub(crate) fn nest<P: Primitive>(rings: &[Layered<P>]) -> Vec<Option<usize>> {
+    let mut events = Vec::new();
+    for (ring_index, layered) in rings.iter().enumerate() {
+        let (vertices, ring) = (layered.vertices, layered.ring);
+        for (&from, &to) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            let ([x, from_y], [_, to_y]) = (vertices[from.into_usize()], vertices[to.into_usize()]);
+            if from_y != to_y {
+                let is_north = to_y < from_y;
+                let key = (
+                    x,
+                    if is_north {
+                        layered.layer
+                    } else {
+                        -layered.layer
+                    },
+                );
+                let (top, bottom) = (from_y.min(to_y), from_y.max(to_y));
+                events.push((bottom, false, key, ring_index, is_north));
+                events.push((top, true, key, ring_index, is_north));
+            }
+        }
+    }
+    events.sort_unstable_by_key(|&(y, is_insertion, ..)| (y, is_insertion));
+
+    let (mut events, mut active) = (events.into_iter().peekable(), BTreeMap::new());
+    let mut parents = Vec::with_capacity(rings.len());
+    for layered in rings {
+        let [_, y] = layered.start();
+        while let Some((_, is_insertion, key, ring_index, is_north)) =
+            events.next_if(|&(event_y, ..)| event_y <= y)
+        {
+            if is_insertion {
+                active.insert(key, (ring_index, is_north));
+            } else {
+                active.remove(&key);
+            }
+        }
+        let parent = active.range(..layered.start_key()).next_back().and_then(
+            |(_, &(crossed_index, is_north))| {
+                let crossed = &rings[crossed_index];
+                if is_outer(crossed.vertices, crossed.ring) == is_north {
+                    Some(crossed_index)
+                } else {
+                    parents[crossed_index]
+                }
+            },
+        );
+        parents.push(parent);
+    }
+    parents
+}
+
+/// Finds the closest ring enclosing each ring in the order [`crate::core::trace()`] yields them.
+pub(crate) fn parents<P: Primitive>(vertices: &[[P; 2]], rings: &[Ring<P>]) -> Vec<Option<usize>> {
+    let rings: Vec<_> = rings
+        .iter()
+        .map(|ring| Layered {
+            vertices,
+            ring,
+            layer: 0,
+        })
+        .collect();
+    nest(&rings)
+}
+
+/// Pairs every outer ring with the holes it is the closest enclosing ring of.
+pub(crate) fn group(is_outer: &[bool], parents: &[Option<usize>]) -> Vec<(usize, Vec<usize>)> {
+    let (mut groups, mut group_indices) = (Vec::new(), Vec::with_capacity(parents.len()));
+    for (index, (&is_outer, &parent)) in is_outer.iter().zip(parents).enumerate() {
+        if is_outer {
+            group_indices.push(groups.len());
+            groups.push((index, Vec::new()));
+        } else {
+            let group_index = parent.map_or(usize::MAX, |parent| group_indices[parent]);
+            group_indices.push(group_index);
+            if let Some((_, holes)) = groups.get_mut(group_index) {
+                holes.push(index);
+            }
+        }
+    }
+    groups
+}
+
+// Vertices of pixel shapes have the same type as indices.
+#[allow(clippy::mismatching_type_param_order)]
+impl<P: Primitive> Shapes<P, P> {
+    pub(crate) fn new(vertices: Vec<[P; 2]>, mut rings: Vec<Ring<P>>, winding: Winding) -> Self {
+        let parents = parents(&vertices, &rings);
+        let is_outer: Vec<bool> = rings.iter().map(|ring| is_outer(&vertices, ring)).collect();
+        let mut take = |index: usize| wind(mem::take(&mut rings[index]), winding);
+        let shapes = group(&is_outer, &parents)
+            .into_iter()
+            .map(|(outer, holes)| Shape {
+                outer: take(outer),
+                holes: holes.into_iter().map(&mut take).collect(),
+            })
+            .collect();
+        Self { vertices, shapes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{
+            tests::{is_inside, pixels, signed_area},
+            Connectivity,
+        },
+        Algorithm, Args,
+    };
+
+    #[test]
+    fn outer_rings_and_holes_wind_opposite_ways() {
+        for (seed, (width, height)) in (1..).zip([(1, 1), (5, 4), (16, 12), (30, 25)]) {
+            let pixels = pixels(seed, width * height);
+            let num_filled = pixels.iter().filter(|&&pixel| pixel).count();
+            for connectivity in [Connectivity::Four, Connectivity::Eight] {
+                for (winding, sign) in [(Winding::CounterClockwise, 1), (Winding::Clockwise, -1)] {
+                    let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+                    let shapes =
+                        algorithm.shapes(Args::new(width, height, pixels.iter().copied()), winding);
+                    let ring = |ring: &Ring| -> Vec<[u16; 2]> {
+                        ring.iter()
+                            .map(|&index| shapes.vertices[usize::from(index)])
+                            .collect()
+                    };
+                    let mut area = 0;
+                    for shape in &shapes.shapes {
+                        let outer = ring(&shape.outer);
+                        assert!(
+                            sign * signed_area(&outer) > 0,
+                            "{connectivity:?} {winding:?}"
+                        );
+                        area += sign * signed_area(&outer);
+                        for hole in &shape.holes {
+                            let hole = ring(hole);
+                            assert!(
+                                sign * signed_area(&hole) < 0,
+                                "{connectivity:?} {winding:?}"
+                            );
+                            area += sign * signed_area(&hole);
+                            let [x, y] = hole[0].map(usize::from);
+                            // The pixel below and to the right of the top-left corner of a hole
+                            // is in it and in its outer ring.
+                            assert!(is_inside(&hole, [x, y]) && is_inside(&outer, [x, y]));
+                        }
+                    }
+                    assert_eq!(area, i64::try_from(num_filled).unwrap());
+                }
+            }
+        }
+    }
+}
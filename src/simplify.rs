@@ -0,0 +1,807 @@
+use crate::{
+    core::Primitive,
+    shapes::{Ring, Shapes},
+};
+use ::alloc::{collections::BinaryHeap, vec, vec::Vec};
+use ::core::{cmp::Reverse, iter, mem};
+use coordinate::Coordinate;
+
+mod coordinate {
+    /// Vertex coordinates of shapes that can be simplified.
+    pub trait Coordinate: Copy {
+        fn into_f64(self) -> f64;
+    }
+
+    macro_rules! impl_coordinate {
+        ($($coordinate:ty),*) => {$(
+            impl Coordinate for $coordinate {
+                #[inline]
+                fn into_f64(self) -> f64 {
+                    self.into()
+                }
+            }
+        )*};
+    }
+
+    impl_coordinate!(u16, u32, f32);
+
+    /// Rounded above `2^53`.
+    impl Coordinate for u64 {
+        #[inline]
+        #[allow(clippy::cast_precision_loss)]
+        fn into_f64(self) -> f64 {
+            self as _
+        }
+    }
+}
+
+type Point = [f64; 2];
+
+fn cross(origin: Point, a: Point, b: Point) -> f64 {
+    (a[0] - origin[0]) * (b[1] - origin[1]) - (a[1] - origin[1]) * (b[0] - origin[0])
+}
+
+fn is_within(a: Point, b: Point, point: Point) -> bool {
+    a[0].min(b[0]) <= point[0]
+        && point[0] <= a[0].max(b[0])
+        && a[1].min(b[1]) <= point[1]
+        && point[1] <= a[1].max(b[1])
+}
+
+/// Whether two segments cross or touch anywhere but at a shared end.
+// Ends are vertices of the same shapes, so shared ones are equal exactly.
+#[allow(clippy::float_cmp)]
+fn is_conflicting([a, b]: [Point; 2], [c, d]: [Point; 2]) -> bool {
+    let (ab_c, ab_d, cd_a, cd_b) = (
+        cross(a, b, c),
+        cross(a, b, d),
+        cross(c, d, a),
+        cross(c, d, b),
+    );
+    if ab_c * ab_d < 0.0 && cd_a * cd_b < 0.0 {
+        return true;
+    }
+    if ab_c == 0.0 && ab_d == 0.0 {
+        let axis = usize::from((b[0] - a[0]).abs() < (b[1] - a[1]).abs());
+        let (ab_min, ab_max) = (a[axis].min(b[axis]), a[axis].max(b[axis]));
+        let (cd_min, cd_max) = (c[axis].min(d[axis]), c[axis].max(d[axis]));
+        return ab_min.max(cd_min) < ab_max.min(cd_max);
+    }
+    let touches = |is_collinear: bool, a: Point, b: Point, point: Point| {
+        is_collinear && is_within(a, b, point) && point != a && point != b
+    };
+    touches(ab_c == 0.0, a, b, c)
+        || touches(ab_d == 0.0, a, b, d)
+        || touches(cd_a == 0.0, c, d, a)
+        || touches(cd_b == 0.0, c, d, b)
+}
+
+/// Whether `point` is inside the closed polygon and not on its boundary.
+fn is_strictly_inside(polygon: impl Iterator<Item = Point> + Clone, point: Point) -> bool {
+    let mut is_inside = false;
+    for (a, b) in polygon.clone().zip(polygon.cycle().skip(1)) {
+        if cross(a, b, point) == 0.0 && is_within(a, b, point) {
+            return false;
+        }
+        if (a[1] > point[1]) != (b[1] > point[1])
+            && point[0] < a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+        {
+            is_inside = !is_inside;
+        }
+    }
+    is_inside
+}
+
+fn distance_squared_to_segment(a: Point, b: Point, point: Point) -> f64 {
+    let (ab, ap) = (
+        [b[0] - a[0], b[1] - a[1]],
+        [point[0] - a[0], point[1] - a[1]],
+    );
+    let length_squared = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        ((ap[0] * ab[0] + ap[1] * ab[1]) / length_squared).clamp(0.0, 1.0)
+    };
+    let offset = [ap[0] - t * ab[0], ap[1] - t * ab[1]];
+    offset[0] * offset[0] + offset[1] * offset[1]
+}
+
+/// Buckets of ring positions by the cells their points or segments cover.
+struct Grid {
+    min: Point,
+    cell_size: f64,
+    num_columns: usize,
+    num_rows: usize,
+    cells: Vec<Vec<(usize, usize)>>,
+}
+
+// Offsets from the minimum are never negative.
+#[allow(clippy::cast_sign_loss)]
+impl Grid {
+    fn new<P>(rings: &[Simplified<P>]) -> Self {
+        let (mut min, mut max, mut len) = ([f64::MAX; 2], [f64::MIN; 2], 0);
+        for point in rings.iter().flat_map(|ring| &ring.points) {
+            min = [min[0].min(point[0]), min[1].min(point[1])];
+            max = [max[0].max(point[0]), max[1].max(point[1])];
+            len += 1;
+        }
+        let extent = (max[0] - min[0]).max(max[1] - min[1]).max(1.0);
+        let num_cells = f64::from(usize::isqrt(len).clamp(1, 1024) as u32);
+        let cell_size = extent / num_cells;
+        let num_columns = ((max[0] - min[0]) / cell_size) as usize + 1;
+        let num_rows = ((max[1] - min[1]) / cell_size) as usize + 1;
+        Self {
+            min,
+            cell_size,
+            num_columns,
+            num_rows,
+            cells: vec![Vec::new(); num_columns * num_rows],
+        }
+    }
+
+    fn cell_indices(&self, a: Point, b: Point) -> impl Iterator<Item = usize> {
+        let cell = |value: f64, min: f64, len: usize| {
+            (((value - min) / self.cell_size).max(0.0) as usize).min(len - 1)
+        };
+        let columns = cell(a[0].min(b[0]), self.min[0], self.num_columns)
+            ..=cell(a[0].max(b[0]), self.min[0], self.num_columns);
+        let rows = cell(a[1].min(b[1]), self.min[1], self.num_rows)
+            ..=cell(a[1].max(b[1]), self.min[1], self.num_rows);
+        let num_columns = self.num_columns;
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .map(move |column| row * num_columns + column)
+        })
+    }
+
+    fn insert(&mut self, a: Point, b: Point, item: (usize, usize)) {
+        for cell_index in self.cell_indices(a, b).collect::<Vec<_>>() {
+            self.cells[cell_index].push(item);
+        }
+    }
+
+    fn query(&self, a: Point, b: Point) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cell_indices(a, b)
+            .flat_map(|cell_index| self.cells[cell_index].iter().copied())
+    }
+}
+
+/// Ring being simplified, with kept original vertices linked into a loop.
+struct Simplified<'a, P> {
+    points: Vec<Point>,
+    ring: &'a mut Ring<P>,
+    kept: Vec<bool>,
+    prev: Vec<usize>,
+    next: Vec<usize>,
+    max_deviation_squared: f64,
+}
+
+impl<'a, P: Primitive> Simplified<'a, P> {
+    fn new<T: Coordinate>(vertices: &[[T; 2]], ring: &'a mut Ring<P>) -> Self {
+        let points: Vec<_> = ring
+            .iter()
+            .map(|&index| {
+                let [x, y] = vertices[index.into_usize()];
+                [x.into_f64(), y.into_f64()]
+            })
+            .collect();
+        let len = points.len();
+        Self {
+            points,
+            ring,
+            kept: vec![true; len],
+            prev: (0..len)
+                .map(|position| (position + len - 1) % len)
+                .collect(),
+            next: (0..len).map(|position| (position + 1) % len).collect(),
+            max_deviation_squared: f64::INFINITY,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.kept.iter().filter(|&&kept| kept).count()
+    }
+
+    fn segment(&self, from: usize) -> [Point; 2] {
+        [self.points[from], self.points[self.next[from]]]
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (usize, [Point; 2])> + '_ {
+        (0..self.kept.len())
+            .filter(|&position| self.kept[position])
+            .map(|from| (from, self.segment(from)))
+    }
+
+    /// Original positions after `from` up to the next kept one.
+    fn skipped(&self, from: usize) -> impl Iterator<Item = usize> + Clone {
+        let (to, len) = (self.next[from], self.kept.len());
+        (1..(to + len - from) % len).map(move |offset| (from + offset) % len)
+    }
+
+    fn signed_area(&self) -> f64 {
+        self.segments()
+            .map(|(_, [a, b])| a[0] * b[1] - b[0] * a[1])
+            .sum()
+    }
+
+    fn keep(&mut self, from: usize, position: usize) {
+        let to = self.next[from];
+        self.kept[position] = true;
+        self.prev[position] = from;
+        self.next[position] = to;
+        self.next[from] = position;
+        self.prev[to] = position;
+    }
+
+    fn remove(&mut self, position: usize) {
+        let (from, to) = (self.prev[position], self.next[position]);
+        self.kept[position] = false;
+        self.next[from] = to;
+        self.prev[to] = from;
+    }
+
+    /// Original position furthest from the kept segment starting at `from`, with its squared
+    /// distance.
+    fn furthest(&self, from: usize) -> Option<(usize, f64)> {
+        let [a, b] = self.segment(from);
+        self.skipped(from)
+            .map(|position| {
+                let distance = distance_squared_to_segment(a, b, self.points[position]);
+                (position, distance)
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Keeps original vertices until every one of them is close enough to the kept segments
+    /// starting at `froms`.
+    fn refine(&mut self, froms: impl IntoIterator<Item = usize>) {
+        let mut stack: Vec<_> = froms.into_iter().collect();
+        while let Some(from) = stack.pop() {
+            if let Some((position, distance)) = self.furthest(from) {
+                if distance > self.max_deviation_squared {
+                    self.keep(from, position);
+                    stack.extend([from, position]);
+                }
+            }
+        }
+    }
+
+    /// Keeps the original vertex furthest from the kept segment starting at `from`.
+    fn split(&mut self, from: usize) -> bool {
+        match self.furthest(from) {
+            Some((position, _)) => {
+                self.keep(from, position);
+                self.refine([from, position]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn douglas_peucker(&mut self, max_deviation: f64) {
+        self.max_deviation_squared = max_deviation * max_deviation;
+        let len = self.kept.len();
+        let distance = |position: usize| {
+            distance_squared_to_segment(self.points[0], self.points[0], self.points[position])
+        };
+        let far = (1..len)
+            .max_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+            .unwrap_or(0);
+        self.kept.fill(false);
+        self.kept[0] = true;
+        (self.next[0], self.prev[0]) = (0, 0);
+        self.keep(0, far);
+        self.refine([0, far]);
+        while self.len() < len.min(3) {
+            let (from, _) = (0..len)
+                .filter(|&from| self.kept[from])
+                .filter_map(|from| self.furthest(from).map(|(_, distance)| (from, distance)))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .unwrap_or_default();
+            self.split(from);
+        }
+    }
+
+    fn finish(self) {
+        let kept = self.kept;
+        let mut position = 0;
+        self.ring.0.retain(|_| {
+            position += 1;
+            kept[position - 1]
+        });
+    }
+}
+
+fn simplified<T: Coordinate, P: Primitive>(shapes: &mut Shapes<T, P>) -> Vec<Simplified<'_, P>> {
+    let vertices = &shapes.vertices;
+    shapes
+        .shapes
+        .iter_mut()
+        .flat_map(|shape| iter::once(&mut shape.outer).chain(&mut shape.holes))
+        .map(|ring| Simplified::new(vertices, ring))
+        .collect()
+}
+
+/// Keeps splitting segments of `rings` until none of them cross or touch, and no kept vertex
+/// lies between a segment and the part of the outline it replaces.
+fn untangle<P: Primitive>(rings: &mut [Simplified<P>]) {
+    loop {
+        let mut segments: Vec<_> = rings
+            .iter()
+            .enumerate()
+            .flat_map(|(ring_index, ring)| {
+                ring.segments()
+                    .map(move |(from, segment)| (ring_index, from, segment))
+            })
+            .collect();
+        segments.sort_by(|(.., a), (.., b)| a[0][0].min(a[1][0]).total_cmp(&b[0][0].min(b[1][0])));
+        let mut splits = Vec::new();
+        for (index, &(ring_index, from, segment)) in segments.iter().enumerate() {
+            let max_x = segment[0][0].max(segment[1][0]);
+            for &(other_ring_index, other_from, other) in &segments[index + 1..] {
+                if other[0][0].min(other[1][0]) > max_x {
+                    break;
+                }
+                if is_conflicting(segment, other) {
+                    splits.extend([(ring_index, from), (other_ring_index, other_from)]);
+                }
+            }
+        }
+
+        let mut grid = Grid::new(rings);
+        for (ring_index, ring) in rings.iter().enumerate() {
+            for (position, &point) in ring.points.iter().enumerate() {
+                if ring.kept[position] {
+                    grid.insert(point, point, (ring_index, position));
+                }
+            }
+        }
+        for &(ring_index, from, _) in &segments {
+            let ring = &rings[ring_index];
+            let outline = iter::once(from)
+                .chain(ring.skipped(from))
+                .chain(iter::once(ring.next[from]))
+                .map(|position| ring.points[position]);
+            let (min, max) =
+                outline
+                    .clone()
+                    .fold(([f64::MAX; 2], [f64::MIN; 2]), |(min, max), point| {
+                        (
+                            [min[0].min(point[0]), min[1].min(point[1])],
+                            [max[0].max(point[0]), max[1].max(point[1])],
+                        )
+                    });
+            let is_covering = grid.query(min, max).any(|(other_ring_index, position)| {
+                let other = &rings[other_ring_index];
+                (other_ring_index != ring_index
+                    || (position != from && position != ring.next[from]))
+                    && is_strictly_inside(outline.clone(), other.points[position])
+            });
+            if is_covering {
+                splits.push((ring_index, from));
+            }
+        }
+
+        let mut is_split = false;
+        for (ring_index, from) in splits {
+            is_split |= rings[ring_index].split(from);
+        }
+        if !is_split {
+            break;
+        }
+    }
+}
+
+/// Whether removing the kept vertex at `position` of a ring would make it cross, touch or jump
+/// over a kept vertex or segment.
+fn blocks_removal<P: Primitive>(
+    rings: &[Simplified<P>],
+    grid: &Grid,
+    ring_index: usize,
+    position: usize,
+) -> bool {
+    let ring = &rings[ring_index];
+    let (prev, next) = (ring.prev[position], ring.next[position]);
+    let (a, b, c) = (ring.points[prev], ring.points[position], ring.points[next]);
+    let min = [a[0].min(b[0]).min(c[0]), a[1].min(b[1]).min(c[1])];
+    let max = [a[0].max(b[0]).max(c[0]), a[1].max(b[1]).max(c[1])];
+    grid.query(min, max)
+        .any(|(other_ring_index, other_position)| {
+            let other = &rings[other_ring_index];
+            let is_own = |positions: &[usize]| {
+                other_ring_index == ring_index && positions.contains(&other_position)
+            };
+            other.kept[other_position]
+                && (!is_own(&[prev, position, next])
+                    && is_strictly_inside([a, b, c].into_iter(), other.points[other_position])
+                    || !is_own(&[prev, position])
+                        && is_conflicting([a, c], other.segment(other_position)))
+        })
+}
+
+/// Kept vertices by the area of the triangle they make with their neighbors, smallest first.
+struct Queue {
+    heap: BinaryHeap<Reverse<(u64, usize, usize, u32)>>,
+    /// Entries of a vertex are stale unless they have its stamp.
+    stamps: Vec<Vec<u32>>,
+    /// Vertices left in place because of topology, retried after removals around them.
+    waiting: Vec<Vec<bool>>,
+    num_waiting: usize,
+}
+
+impl Queue {
+    fn new<P>(rings: &[Simplified<P>]) -> Self {
+        let mut queue = Self {
+            heap: BinaryHeap::new(),
+            stamps: rings.iter().map(|ring| vec![0; ring.kept.len()]).collect(),
+            waiting: rings
+                .iter()
+                .map(|ring| vec![false; ring.kept.len()])
+                .collect(),
+            num_waiting: 0,
+        };
+        for (ring_index, ring) in rings.iter().enumerate() {
+            for position in 0..ring.kept.len() {
+                queue.push(rings, ring_index, position);
+            }
+        }
+        queue
+    }
+
+    fn is_waiting(&self, ring_index: usize, position: usize) -> bool {
+        self.waiting[ring_index][position]
+    }
+
+    /// Adds the vertex, replacing its earlier entries.
+    fn push<P>(&mut self, rings: &[Simplified<P>], ring_index: usize, position: usize) {
+        if mem::take(&mut self.waiting[ring_index][position]) {
+            self.num_waiting -= 1;
+        }
+        let stamp = &mut self.stamps[ring_index][position];
+        *stamp += 1;
+        let ring = &rings[ring_index];
+        let (prev, next) = (
+            ring.points[ring.prev[position]],
+            ring.points[ring.next[position]],
+        );
+        let significance = cross(prev, ring.points[position], next).abs().to_bits();
+        self.heap
+            .push(Reverse((significance, ring_index, position, *stamp)));
+    }
+
+    fn pop<P>(&mut self, rings: &[Simplified<P>]) -> Option<(usize, usize)> {
+        while let Some(Reverse((_, ring_index, position, stamp))) = self.heap.pop() {
+            if rings[ring_index].kept[position] && self.stamps[ring_index][position] == stamp {
+                return Some((ring_index, position));
+            }
+        }
+        None
+    }
+
+    fn wait(&mut self, ring_index: usize, position: usize) {
+        self.waiting[ring_index][position] = true;
+        self.num_waiting += 1;
+    }
+
+    /// Adds every waiting vertex again, returns whether there were any.
+    fn retry<P>(&mut self, rings: &[Simplified<P>]) -> bool {
+        if self.num_waiting == 0 {
+            return false;
+        }
+        for (ring_index, ring) in rings.iter().enumerate() {
+            for position in 0..ring.kept.len() {
+                if self.waiting[ring_index][position] {
+                    self.push(rings, ring_index, position);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Maximum number of vertices left by [`Shapes::reduce()`], never less than 3 per ring.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Budget {
+    PerRing(usize),
+    Total(usize),
+}
+
+/// Outcome of [`Shapes::reduce()`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Reduction {
+    /// Number of vertices left in all rings.
+    pub len: usize,
+    /// Sum of absolute changes of ring areas.
+    pub area_error: f64,
+    /// Whether every ring is within the [`Budget`], which rings in the way of each other or
+    /// holding less area than their vertices can keep from happening.
+    pub is_budget_met: bool,
+}
+
+impl<T: Coordinate, P: Primitive> Shapes<T, P> {
+    /// Removes vertices with the Douglas–Peucker algorithm, so that no ring moves further than
+    /// `max_deviation` from its original outline, while keeping rings from crossing, touching
+    /// or jumping over each other or themselves anywhere they did not before.
+    ///
+    /// Vertices stay in place, only indices are removed from rings.
+    pub fn simplify(&mut self, max_deviation: f64) {
+        let mut rings = simplified(self);
+        for ring in &mut rings {
+            ring.douglas_peucker(max_deviation);
+        }
+        untangle(&mut rings);
+        rings.into_iter().for_each(Simplified::finish);
+    }
+
+    /// Removes vertices with the Visvalingam–Whyatt algorithm until `budget` is met, least
+    /// significant first, with the same topology guarantees as [`Self::simplify()`].
+    ///
+    /// A vertex whose removal would cross, touch or jump over another ring, or flip its own,
+    /// is retried once vertices around it are gone, but topology can still keep the budget from
+    /// being met, which [`Reduction::is_budget_met`] tells.
+    ///
+    /// Vertices stay in place, only indices are removed from rings.
+    pub fn reduce(&mut self, budget: Budget) -> Reduction {
+        let mut rings = simplified(self);
+        let mut grid = Grid::new(&rings);
+        for (ring_index, ring) in rings.iter().enumerate() {
+            for (position, &point) in ring.points.iter().enumerate() {
+                grid.insert(point, point, (ring_index, position));
+                let [a, b] = ring.segment(position);
+                grid.insert(a, b, (ring_index, position));
+            }
+        }
+        let original_areas: Vec<f64> = rings.iter().map(Simplified::signed_area).collect();
+        let mut areas = original_areas.clone();
+        let mut lens: Vec<usize> = rings.iter().map(Simplified::len).collect();
+        let mut len: usize = lens.iter().sum();
+        let mut queue = Queue::new(&rings);
+        let mut is_changed = false;
+
+        loop {
+            let Some((ring_index, position)) = queue.pop(&rings) else {
+                // Removals may have unblocked vertices not around them, like by changing the
+                // area of their ring.
+                if !mem::take(&mut is_changed) || !queue.retry(&rings) {
+                    break;
+                }
+                continue;
+            };
+            match budget {
+                Budget::PerRing(max_len) if lens[ring_index] <= max_len.max(3) => continue,
+                Budget::Total(max_len) if len <= max_len => break,
+                _ if lens[ring_index] <= 3 => continue,
+                _ => {}
+            }
+            let ring = &rings[ring_index];
+            let (prev, next) = (ring.prev[position], ring.next[position]);
+            let (a, b, c) = (ring.points[prev], ring.points[position], ring.points[next]);
+            let area = areas[ring_index] - cross(a, b, c);
+            if area == 0.0
+                || (area > 0.0) != (original_areas[ring_index] > 0.0)
+                || blocks_removal(&rings, &grid, ring_index, position)
+            {
+                queue.wait(ring_index, position);
+                continue;
+            }
+
+            rings[ring_index].remove(position);
+            grid.insert(a, c, (ring_index, prev));
+            areas[ring_index] = area;
+            lens[ring_index] -= 1;
+            len -= 1;
+            is_changed = true;
+            // Waiting vertices with a segment around the removed one, and the neighbors.
+            let min = [a[0].min(b[0]).min(c[0]), a[1].min(b[1]).min(c[1])];
+            let max = [a[0].max(b[0]).max(c[0]), a[1].max(b[1]).max(c[1])];
+            let mut changed: Vec<_> = grid
+                .query(min, max)
+                .filter(|&(other_ring_index, other_position)| {
+                    rings[other_ring_index].kept[other_position]
+                })
+                .flat_map(|(other_ring_index, other_position)| {
+                    let other = &rings[other_ring_index];
+                    [other_position, other.next[other_position]]
+                        .map(|other_position| (other_ring_index, other_position))
+                })
+                .filter(|&(other_ring_index, other_position)| {
+                    queue.is_waiting(other_ring_index, other_position)
+                })
+                .collect();
+            changed.extend([(ring_index, prev), (ring_index, next)]);
+            for (ring_index, position) in changed {
+                queue.push(&rings, ring_index, position);
+            }
+        }
+        let is_budget_met = match budget {
+            Budget::PerRing(max_len) => lens.iter().all(|&len| len <= max_len.max(3)),
+            Budget::Total(max_len) => len <= max_len,
+        };
+        rings.into_iter().for_each(Simplified::finish);
+        Reduction {
+            len,
+            area_error: areas
+                .iter()
+                .zip(&original_areas)
+                .map(|(area, original_area)| (area - original_area).abs() * 0.5)
+                .sum(),
+            is_budget_met,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::tests::pixels, Algorithm, Args, Winding};
+
+    type Segment = [[i64; 2]; 2];
+
+    fn shapes(seed: u64, width: usize, height: usize) -> Shapes {
+        let pixels = pixels(seed, width * height);
+        Algorithm::new().shapes(Args::new(width, height, pixels), Winding::CounterClockwise)
+    }
+
+    fn segments(shapes: &Shapes) -> Vec<Segment> {
+        let vertex = |index: u16| shapes.vertices[usize::from(index)].map(i64::from);
+        shapes
+            .rings()
+            .flat_map(|ring| {
+                ring.iter()
+                    .zip(ring.iter().cycle().skip(1))
+                    .map(move |(&from, &to)| [vertex(from), vertex(to)])
+            })
+            .collect()
+    }
+
+    fn orientation(a: [i64; 2], b: [i64; 2], c: [i64; 2]) -> i64 {
+        ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])).signum()
+    }
+
+    /// Whether `point` is on `segment`, ends included.
+    fn is_on([a, b]: Segment, point: [i64; 2]) -> bool {
+        orientation(a, b, point) == 0
+            && a[0].min(b[0]) <= point[0]
+            && point[0] <= a[0].max(b[0])
+            && a[1].min(b[1]) <= point[1]
+            && point[1] <= a[1].max(b[1])
+    }
+
+    /// Whether two segments have any point in common other than a shared end.
+    fn is_touching(first: Segment, second: Segment) -> bool {
+        let [a, b] = first;
+        let [c, d] = second;
+        if (a == c && b == d) || (a == d && b == c) {
+            return true;
+        }
+        let is_crossing = orientation(a, b, c) * orientation(a, b, d) < 0
+            && orientation(c, d, a) * orientation(c, d, b) < 0;
+        let is_end_on =
+            |segment: Segment, point: [i64; 2]| is_on(segment, point) && !segment.contains(&point);
+        is_crossing
+            || is_end_on(first, c)
+            || is_end_on(first, d)
+            || is_end_on(second, a)
+            || is_end_on(second, b)
+    }
+
+    fn num_touching(shapes: &Shapes) -> usize {
+        let segments = segments(shapes);
+        segments
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &first)| {
+                segments[index + 1..]
+                    .iter()
+                    .filter(move |&&second| is_touching(first, second))
+            })
+            .count()
+    }
+
+    fn twice_area(vertices: &[[u16; 2]], ring: &[u16]) -> i64 {
+        ring.iter()
+            .zip(ring.iter().cycle().skip(1))
+            .map(|(&from, &to)| {
+                let ([x0, y0], [x1, y1]) = (vertices[usize::from(from)], vertices[usize::from(to)]);
+                i64::from(x0) * i64::from(y1) - i64::from(x1) * i64::from(y0)
+            })
+            .sum()
+    }
+
+    /// Whether `point` is at most `max_distance` from the segment from `a` to `b`, compared
+    /// squared so that integer coordinates are exact.
+    fn is_near([a, b]: [[f64; 2]; 2], point: [f64; 2], max_distance: f64) -> bool {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let (px, py) = (point[0] - a[0], point[1] - a[1]);
+        let (dot, len_squared) = (px * dx + py * dy, dx * dx + dy * dy);
+        let max_squared = max_distance * max_distance;
+        if dot <= 0.0 {
+            px * px + py * py <= max_squared
+        } else if dot >= len_squared {
+            let (qx, qy) = (point[0] - b[0], point[1] - b[1]);
+            qx * qx + qy * qy <= max_squared
+        } else {
+            let cross = px * dy - py * dx;
+            cross * cross <= max_squared * len_squared
+        }
+    }
+
+    #[test]
+    fn simplify_keeps_deviation_and_topology() {
+        for (seed, max_deviation) in (1..).zip([0.5, 1.0, 1.5, 3.0]) {
+            let original = shapes(seed, 24, 19);
+            assert_eq!(num_touching(&original), 0);
+            let mut simplified = original.clone();
+            simplified.simplify(max_deviation);
+            assert_eq!(num_touching(&simplified), 0, "{max_deviation}");
+            let point = |index: u16| original.vertices[usize::from(index)].map(f64::from);
+            for (ring, simplified_ring) in original.rings().zip(simplified.rings()) {
+                assert!(simplified_ring.len() >= 3);
+                assert_eq!(
+                    twice_area(&original.vertices, ring).signum(),
+                    twice_area(&original.vertices, simplified_ring).signum()
+                );
+                // Kept vertices are in the same order, every removed one close to the segment
+                // replacing it.
+                let start = ring.iter().position(|&index| index == simplified_ring[0]);
+                let mut original_indices = ring.iter().cycle().skip(start.unwrap()).peekable();
+                for (&from, &to) in simplified_ring
+                    .iter()
+                    .zip(simplified_ring.iter().cycle().skip(1))
+                {
+                    assert_eq!(original_indices.next(), Some(&from));
+                    let segment = [point(from), point(to)];
+                    while let Some(&index) = original_indices.next_if(|&&index| index != to) {
+                        assert!(is_near(segment, point(index), max_deviation));
+                    }
+                }
+                assert_eq!(original_indices.next(), Some(&simplified_ring[0]));
+            }
+        }
+    }
+
+    #[test]
+    fn reduce_meets_budget_with_area_error() {
+        for (seed, (budget, is_met)) in (1..).zip([
+            (Budget::PerRing(3), true),
+            (Budget::PerRing(6), true),
+            (Budget::Total(0), false),
+            (Budget::Total(200), true),
+            (Budget::Total(usize::MAX), true),
+        ]) {
+            let original = shapes(seed, 24, 19);
+            let mut reduced = original.clone();
+            let reduction = reduced.reduce(budget);
+            assert_eq!(num_touching(&reduced), 0, "{budget:?}");
+            let lens: Vec<usize> = reduced.rings().map(|ring| ring.len()).collect();
+            assert_eq!(reduction.len, lens.iter().sum::<usize>());
+            let is_budget_met = match budget {
+                Budget::PerRing(max_len) => lens.iter().all(|&len| len <= max_len.max(3)),
+                Budget::Total(max_len) => reduction.len <= max_len,
+            };
+            assert_eq!(reduction.is_budget_met, is_budget_met, "{budget:?}");
+            assert_eq!(reduction.is_budget_met, is_met, "{budget:?}");
+
+            if budget == Budget::Total(usize::MAX) {
+                assert_eq!(reduced, original);
+            }
+            let mut area_error = 0;
+            for (ring, reduced_ring) in original.rings().zip(reduced.rings()) {
+                let (area, reduced_area) = (
+                    twice_area(&original.vertices, ring),
+                    twice_area(&original.vertices, reduced_ring),
+                );
+                assert!(reduced_ring.len() >= 3);
+                assert_eq!(area.signum(), reduced_area.signum());
+                area_error += (area - reduced_area).unsigned_abs();
+            }
+            let area_error = f64::from(u32::try_from(area_error).unwrap()) * 0.5;
+            assert!(
+                (reduction.area_error - area_error).abs() < 1e-9,
+                "{budget:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::core::{Connectivity, Primitive};
+use ::alloc::{
+    collections::VecDeque,
+    vec::{self, Vec},
+};
+use ::core::mem;
+
+/// Sides of a vertex an edge can go to.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Side {
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl Side {
+    /// Side an edge leaving with a heading turned clockwise from the one arriving from `self`
+    /// goes to.
+    const fn clockwise(self) -> Self {
+        match self {
+            Self::Up => Self::Left,
+            Self::Left => Self::Down,
+            Self::Down => Self::Right,
+            Self::Right => Self::Up,
+        }
+    }
+
+    /// Side an edge leaving with a heading turned counter-clockwise from the one arriving from
+    /// `self` goes to.
+    const fn counter_clockwise(self) -> Self {
+        match self {
+            Self::Up => Self::Right,
+            Self::Left => Self::Up,
+            Self::Down => Self::Left,
+            Self::Right => Self::Down,
+        }
+    }
+}
+
+/// Where the open last end of a chain waits for the next vertex.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Port {
+    Column(usize),
+    Row,
+}
+
+/// Part of a ring, its last vertex is left through `tail`.
+#[derive(Clone, Debug)]
+struct Chain<P> {
+    vertices: VecDeque<[P; 2]>,
+    tail: Port,
+}
+
+/// Extracts rings from an image pushed one row at a time, keeping only the previous row and
+/// the rings that are still open.
+///
+/// Rings are the same as [`crate::core::trace()`] yields, as lists of vertices instead of
+/// indices, starting at their top-left-most vertex.
+#[derive(Clone, Debug, Default)]
+pub struct Stream<P = u16> {
+    connectivity: Connectivity,
+    width: Option<usize>,
+    num_rows: usize,
+    above: Vec<bool>,
+    below: Vec<bool>,
+    /// Chain ending at the vertical edge going down from every vertex of the last line.
+    columns: Vec<Option<usize>>,
+    chains: Vec<Option<Chain<P>>>,
+    free_chains: Vec<usize>,
+    rings: Vec<Vec<[P; 2]>>,
+}
+
+impl Stream {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            connectivity: Connectivity::Four,
+            width: None,
+            num_rows: 0,
+            above: Vec::new(),
+            below: Vec::new(),
+            columns: Vec::new(),
+            chains: Vec::new(),
+            free_chains: Vec::new(),
+            rings: Vec::new(),
+        }
+    }
+}
+
+impl<P: Primitive> Stream<P> {
+    /// [`Connectivity::Average`] is the same as [`Connectivity::Four`] here.
+    #[must_use]
+    pub const fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.connectivity = connectivity;
+        self
+    }
+
+    #[must_use]
+    pub const fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+
+    /// Width of the image, set by the first row.
+    #[must_use]
+    pub const fn width(&self) -> Option<usize> {
+        self.width
+    }
+
+    /// Adds the next row of pixels and returns rings closed by it, padding it with empty pixels
+    /// or cutting it to the width of the first row.
+    pub fn push_row(&mut self, row: impl IntoIterator<Item = bool>) -> vec::Drain<'_, Vec<[P; 2]>> {
+        self.below.clear();
+        if let Some(width) = self.width {
+            self.below.extend(row.into_iter().take(width));
+            self.below.resize(width, false);
+        } else {
+            self.below.extend(row);
+            self.width = Some(self.below.len());
+            self.above.resize(self.below.len(), false);
+            self.columns.resize(self.below.len() + 1, None);
+        }
+        self.push_line();
+        mem::swap(&mut self.above, &mut self.below);
+        self.num_rows += 1;
+        self.rings.drain(..)
+    }
+
+    /// Closes the remaining rings below the last row and returns them, so that another image
+    /// can be pushed.
+    pub fn finish(&mut self) -> vec::Drain<'_, Vec<[P; 2]>> {
+        self.below.clear();
+        self.below.resize(self.above.len(), false);
+        self.push_line();
+        self.width = None;
+        self.num_rows = 0;
+        self.above.clear();
+        self.columns.clear();
+        self.chains.clear();
+        self.free_chains.clear();
+        self.rings.drain(..)
+    }
+
+    /// Links the edges meeting at every vertex of the line between `above` and `below`.
+    fn push_line(&mut self) {
+        let width = self.above.len();
+        let mut row = None;
+        for x in 0..=width {
+            let pixel =
+                |pixels: &[bool], x: Option<usize>| x.and_then(|x| pixels.get(x)) == Some(&true);
+            let (top_left, top_right) = (
+                pixel(&self.above, x.checked_sub(1)),
+                pixel(&self.above, Some(x)),
+            );
+            let (bottom_left, bottom_right) = (
+                pixel(&self.below, x.checked_sub(1)),
+                pixel(&self.below, Some(x)),
+            );
+            // Edges keep filled pixels on their right-hand side, `true` if going into the vertex.
+            let edges = [
+                (Side::Up, top_left != top_right, top_left),
+                (Side::Left, top_left != bottom_left, bottom_left),
+                (Side::Down, bottom_left != bottom_right, bottom_right),
+                (Side::Right, top_right != bottom_right, top_right),
+            ];
+            let num_edges = edges.iter().filter(|&&(_, exists, _)| exists).count();
+            let is_straight = (edges[0].1 && edges[2].1) || (edges[1].1 && edges[3].1);
+            if num_edges == 0 || (num_edges == 2 && is_straight) {
+                continue;
+            }
+            let vertex = [P::from_usize(x), P::from_usize(self.num_rows)];
+            let (up, left) = (self.columns[x].take(), row.take());
+            let existing = |side: Side| match side {
+                Side::Up => up,
+                Side::Left => left,
+                Side::Down | Side::Right => None,
+            };
+            let (mut down, mut right) = (None, None);
+            for &(from, exists, is_incoming) in &edges {
+                if !exists || !is_incoming {
+                    continue;
+                }
+                let to = if num_edges == 2 {
+                    edges
+                        .iter()
+                        .find(|&&(_, exists, is_incoming)| exists && !is_incoming)
+                        .map_or(from, |&(side, ..)| side)
+                } else if self.connectivity == Connectivity::Eight {
+                    from.counter_clockwise()
+                } else {
+                    from.clockwise()
+                };
+                let chain = self.link(vertex, [existing(from), existing(to)], to, x, &mut row);
+                for side in [from, to] {
+                    match side {
+                        Side::Down => down = chain,
+                        Side::Right => right = chain,
+                        Side::Up | Side::Left => {}
+                    }
+                }
+            }
+            self.columns[x] = down;
+            if right.is_some() {
+                row = right;
+            }
+        }
+    }
+
+    /// Joins the chain arriving from `from` with the one leaving to `to` at `vertex`, and
+    /// returns the resulting chain unless it closed into a ring.
+    fn link(
+        &mut self,
+        vertex: [P; 2],
+        [from, to]: [Option<usize>; 2],
+        to_side: Side,
+        x: usize,
+        row: &mut Option<usize>,
+    ) -> Option<usize> {
+        let tail = if to_side == Side::Down {
+            Port::Column(x)
+        } else {
+            Port::Row
+        };
+        match (from, to) {
+            (Some(from), Some(to)) if from == to => {
+                let mut chain = self.chains[from].take()?;
+                self.free_chains.push(from);
+                chain.vertices.push_back(vertex);
+                let start = (0..chain.vertices.len())
+                    .min_by_key(|&index| {
+                        let [x, y] = chain.vertices[index];
+                        (y, x)
+                    })
+                    .unwrap_or(0);
+                chain.vertices.rotate_left(start);
+                self.rings.push(chain.vertices.into());
+                None
+            }
+            (Some(from), Some(to)) => {
+                let other = self.chains[to].take()?;
+                self.free_chains.push(to);
+                let chain = self.chains[from].as_mut()?;
+                chain.vertices.push_back(vertex);
+                chain.vertices.extend(other.vertices);
+                chain.tail = other.tail;
+                match other.tail {
+                    Port::Column(column) => self.columns[column] = Some(from),
+                    Port::Row => *row = Some(from),
+                }
+                Some(from)
+            }
+            (Some(from), None) => {
+                let chain = self.chains[from].as_mut()?;
+                chain.vertices.push_back(vertex);
+                chain.tail = tail;
+                Some(from)
+            }
+            (None, Some(to)) => {
+                let chain = self.chains[to].as_mut()?;
+                chain.vertices.push_front(vertex);
+                Some(to)
+            }
+            (None, None) => {
+                let chain = Chain {
+                    vertices: VecDeque::from([vertex]),
+                    tail,
+                };
+                if let Some(index) = self.free_chains.pop() {
+                    self.chains[index] = Some(chain);
+                    Some(index)
+                } else {
+                    self.chains.push(Some(chain));
+                    Some(self.chains.len() - 1)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::tests::{canonical, pixels, rings};
+
+    #[test]
+    fn rings_match_whole_image() {
+        let mut stream = Stream::new();
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            stream = stream.with_connectivity(connectivity);
+            for (seed, (width, height)) in (1..).zip([(1, 1), (2, 3), (9, 4), (16, 16), (40, 7)]) {
+                let pixels = pixels(seed, width * height);
+                let mut streamed = Vec::new();
+                for row in pixels.chunks_exact(width) {
+                    streamed.extend(stream.push_row(row.iter().copied()));
+                }
+                streamed.extend(stream.finish());
+                assert_eq!(
+                    canonical(streamed),
+                    rings(width, height, &pixels, connectivity),
+                    "{width}x{height} {connectivity:?}"
+                );
+            }
+        }
+    }
+}
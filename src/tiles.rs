@@ -0,0 +1,426 @@
+use crate::{
+    core::{Connectivity, Primitive},
+    hierarchy::Hierarchy,
+    incremental::{self, Heading, Point},
+    shapes::{Ring, Shapes, Traced, Winding},
+};
+use ::alloc::{
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+use ::core::mem;
+
+/// Rings of one tile of a larger image, in world coordinates, with their edges on the lines of
+/// the tile grid cut out so that they can be joined with the ones of the neighbouring tiles.
+#[derive(Clone, Debug, Default)]
+pub struct Tile<P = u16> {
+    index: [usize; 2],
+    size: [usize; 2],
+    width: usize,
+    height: usize,
+    connectivity: Connectivity,
+    /// Rings without edges on the grid.
+    rings: Vec<Vec<[P; 2]>>,
+    /// Parts of rings going from one grid line to another.
+    chains: Vec<Vec<[P; 2]>>,
+    /// Top, bottom, left and right pixels of the tile, bottom and right ones only if the tile
+    /// reaches the next grid line.
+    borders: [Vec<bool>; 4],
+}
+
+impl<P: Primitive> Tile<P> {
+    /// Cuts the rings traced from a `width * height` tile at `index` of a grid of `size` tiles.
+    pub(crate) fn new(
+        (vertices, rings): Traced<P>,
+        [width, height]: [usize; 2],
+        size: [usize; 2],
+        index: [usize; 2],
+        connectivity: Connectivity,
+    ) -> Self {
+        let [tile_width, tile_height] = size;
+        let origin = [index[0] * tile_width, index[1] * tile_height];
+        let mut borders = [
+            vec![false; width],
+            if height == tile_height {
+                vec![false; width]
+            } else {
+                Vec::new()
+            },
+            vec![false; height],
+            if width == tile_width {
+                vec![false; height]
+            } else {
+                Vec::new()
+            },
+        ];
+        let (mut tile_rings, mut chains) = (Vec::new(), Vec::new());
+        for ring in rings {
+            let ring: Vec<[usize; 2]> = ring
+                .iter()
+                .map(|&index| vertices[index.into_usize()].map(Primitive::into_usize))
+                .collect();
+            let world =
+                |[x, y]: [usize; 2]| [P::from_usize(origin[0] + x), P::from_usize(origin[1] + y)];
+            // Segments on the grid, marking the pixels next to them on the way.
+            let is_on_grid: Vec<bool> = (0..ring.len())
+                .map(|index| {
+                    let ([from_x, from_y], [to_x, to_y]) =
+                        (ring[index], ring[(index + 1) % ring.len()]);
+                    let border = if from_y == to_y {
+                        match from_y {
+                            0 => Some(0),
+                            y if y == tile_height => Some(1),
+                            _ => None,
+                        }
+                    } else {
+                        match from_x {
+                            0 => Some(2),
+                            x if x == tile_width => Some(3),
+                            _ => None,
+                        }
+                    };
+                    let Some(border) = border else {
+                        return false;
+                    };
+                    let range = if border < 2 {
+                        from_x.min(to_x)..from_x.max(to_x)
+                    } else {
+                        from_y.min(to_y)..from_y.max(to_y)
+                    };
+                    borders[border][range].fill(true);
+                    true
+                })
+                .collect();
+            let Some(start) = is_on_grid.iter().position(|&is_on_grid| is_on_grid) else {
+                tile_rings.push(ring.into_iter().map(world).collect());
+                continue;
+            };
+            let mut chain = Vec::new();
+            for offset in 1..=ring.len() {
+                let index = (start + offset) % ring.len();
+                if is_on_grid[index] {
+                    if !chain.is_empty() {
+                        chain.push(world(ring[index]));
+                        chains.push(mem::take(&mut chain));
+                    }
+                } else {
+                    chain.push(world(ring[index]));
+                }
+            }
+        }
+        Self {
+            index,
+            size,
+            width,
+            height,
+            connectivity,
+            rings: tile_rings,
+            chains,
+            borders,
+        }
+    }
+
+    #[must_use]
+    pub const fn index(&self) -> [usize; 2] {
+        self.index
+    }
+
+    /// Pixel at `[x, y]` inside the tile, which has to be next to a grid line.
+    fn pixel(&self, [x, y]: [usize; 2]) -> bool {
+        let [top, bottom, left, right] = &self.borders;
+        if x >= self.width || y >= self.height {
+            false
+        } else if y == 0 {
+            top[x]
+        } else if y + 1 == self.size[1] {
+            bottom[x]
+        } else if x == 0 {
+            left[y]
+        } else {
+            x + 1 == self.size[0] && right[y]
+        }
+    }
+}
+
+/// Tiles of a grid, stitched together into the rings of the whole image.
+///
+/// Every tile is extracted on its own with [`crate::Algorithm::tile()`], so they can be
+/// extracted on different threads or only once loaded, and inserted in any order.
+#[derive(Clone, Debug, Default)]
+pub struct Tiles<P = u16> {
+    size: [usize; 2],
+    tiles: BTreeMap<[usize; 2], Tile<P>>,
+}
+
+impl Tiles {
+    #[must_use]
+    pub const fn new(size: [usize; 2]) -> Self {
+        Self::with_size(size)
+    }
+}
+
+impl<P: Primitive> Tiles<P> {
+    /// Grid of tiles of `size` pixels, missing tiles are empty.
+    ///
+    /// # Panics
+    /// If `size` is zero.
+    #[must_use]
+    pub const fn with_size(size: [usize; 2]) -> Self {
+        assert!(size[0] > 0 && size[1] > 0, "empty tiles");
+        Self {
+            size,
+            tiles: BTreeMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    /// Adds a tile, replacing the one at the same index.
+    ///
+    /// # Panics
+    /// If the tile was extracted for a grid of another size.
+    pub fn insert(&mut self, tile: Tile<P>) -> Option<Tile<P>> {
+        assert_eq!(tile.size, self.size, "tile of another grid");
+        self.tiles.insert(tile.index, tile)
+    }
+
+    pub fn remove(&mut self, index: [usize; 2]) -> Option<Tile<P>> {
+        self.tiles.remove(&index)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = &Tile<P>> + '_ {
+        self.tiles.values()
+    }
+
+    /// Same as [`crate::Algorithm::shapes()`] on the whole image.
+    #[must_use]
+    pub fn shapes(&self, winding: Winding) -> Shapes<P, P> {
+        let (vertices, rings) = self.trace();
+        Shapes::new(vertices, rings, winding)
+    }
+
+    /// Same as [`crate::Algorithm::hierarchy()`] on the whole image.
+    #[must_use]
+    pub fn hierarchy(&self, winding: Winding) -> Hierarchy<P> {
+        let (vertices, rings) = self.trace();
+        Hierarchy::new(vertices, rings, winding)
+    }
+
+    fn pixel(&self, [x, y]: Point) -> bool {
+        let [tile_width, tile_height] = self.size;
+        if x < 0 || y < 0 {
+            return false;
+        }
+        let [x, y] = [x.cast_unsigned(), y.cast_unsigned()];
+        let index = [x / tile_width, y / tile_height];
+        self.tiles
+            .get(&index)
+            .is_some_and(|tile| tile.pixel([x % tile_width, y % tile_height]))
+    }
+
+    fn is_on_grid(&self, [x, y]: Point, heading: Heading) -> bool {
+        if heading[1] == 0 {
+            y.cast_unsigned().is_multiple_of(self.size[1])
+        } else {
+            x.cast_unsigned().is_multiple_of(self.size[0])
+        }
+    }
+
+    /// Joins the chains of every tile along the grid lines, welding their vertices.
+    fn trace(&self) -> Traced<P> {
+        let point = |[x, y]: [P; 2]| [x.into_usize().cast_signed(), y.into_usize().cast_signed()];
+        let heading = |[from_x, from_y]: Point, [to_x, to_y]: Point| {
+            [(to_x - from_x).signum(), (to_y - from_y).signum()]
+        };
+        let connectivity = self
+            .tiles
+            .values()
+            .next()
+            .map_or(Connectivity::Four, |tile| tile.connectivity);
+        let turn = |vertex: Point, heading: Heading| {
+            incremental::turn(|point| self.pixel(point), connectivity, vertex, heading)
+        };
+        let mut chains: Vec<Option<Vec<Point>>> = Vec::new();
+        let mut heads = BTreeMap::new();
+        let mut rings: Vec<Vec<Point>> = Vec::new();
+        for tile in self.tiles.values() {
+            for chain in &tile.chains {
+                let chain: Vec<Point> = chain.iter().copied().map(point).collect();
+                heads.insert((chain[0], heading(chain[0], chain[1])), chains.len());
+                chains.push(Some(chain));
+            }
+        }
+
+        let mut visited = BTreeSet::new();
+        for index in 0..chains.len() {
+            let mut ring = Vec::new();
+            let mut next = Some(index);
+            while let Some(chain) = next.and_then(|next| chains[next].take()) {
+                let len = chain.len();
+                let (mut vertex, mut from) =
+                    (chain[len - 1], heading(chain[len - 2], chain[len - 1]));
+                ring.extend(chain);
+                next = loop {
+                    let to = turn(vertex, from);
+                    if !self.is_on_grid(vertex, to) {
+                        break heads.get(&(vertex, to)).copied();
+                    }
+                    visited.insert((vertex, to));
+                    ring.push(vertex);
+                    (vertex, from) = (incremental::step(vertex, to), to);
+                };
+            }
+            if !ring.is_empty() {
+                rings.push(ring);
+            }
+        }
+
+        // Rings with every edge on the grid, like single pixels of one pixel wide tiles.
+        let [tile_width, tile_height] = self.size.map(usize::cast_signed);
+        for tile in self.tiles.values() {
+            let [x, y] = [
+                tile.index[0].cast_signed() * tile_width,
+                tile.index[1].cast_signed() * tile_height,
+            ];
+            let [width, height] = [tile.width.cast_signed(), tile.height.cast_signed()];
+            let border = (0..=width)
+                .flat_map(|offset| [[x + offset, y], [x + offset, y + height]])
+                .chain((0..=height).flat_map(|offset| [[x, y + offset], [x + width, y + offset]]));
+            for start in border {
+                for start_heading in incremental::outgoing(|point| self.pixel(point), start)
+                    .into_iter()
+                    .flatten()
+                {
+                    if !self.is_on_grid(start, start_heading)
+                        || !visited.insert((start, start_heading))
+                    {
+                        continue;
+                    }
+                    let mut ring = Vec::from([start]);
+                    let (mut vertex, mut from) =
+                        (incremental::step(start, start_heading), start_heading);
+                    loop {
+                        let to = turn(vertex, from);
+                        if (vertex, to) == (start, start_heading) || !self.is_on_grid(vertex, to) {
+                            break;
+                        }
+                        visited.insert((vertex, to));
+                        ring.push(vertex);
+                        (vertex, from) = (incremental::step(vertex, to), to);
+                    }
+                    rings.push(ring);
+                }
+            }
+        }
+
+        let stitched: Vec<Vec<[P; 2]>> = rings.into_iter().map(incremental::normalize).collect();
+        let rings: Vec<&[[P; 2]]> = self
+            .tiles
+            .values()
+            .flat_map(|tile| &tile.rings)
+            .chain(&stitched)
+            .map(Vec::as_slice)
+            .collect();
+        weld(rings)
+    }
+}
+
+/// Vertices of `rings` without duplicates, sorted by rows like [`crate::core::get()`] finds
+/// them, and the rings as indices into them, sorted by their first vertex.
+fn weld<P: Primitive>(mut rings: Vec<&[[P; 2]]>) -> Traced<P> {
+    rings.sort_unstable_by_key(|ring| {
+        let [x, y] = ring[0];
+        (y, x)
+    });
+    let mut vertices: Vec<[P; 2]> = rings.iter().copied().flatten().copied().collect();
+    vertices.sort_unstable_by_key(|&[x, y]| (y, x));
+    vertices.dedup();
+    assert!(
+        vertices.len().saturating_sub(1) <= P::MAX.into_usize(),
+        "too many vertices"
+    );
+    // Index of the first vertex of every row.
+    let num_rows = vertices.last().map_or(0, |&[_, y]| y.into_usize() + 1);
+    let mut row_starts = vec![0; num_rows + 1];
+    for &[_, y] in &vertices {
+        row_starts[y.into_usize() + 1] += 1;
+    }
+    for y in 0..num_rows {
+        row_starts[y + 1] += row_starts[y];
+    }
+    let rings = rings
+        .into_iter()
+        .map(|ring| {
+            Ring::from(
+                ring.iter()
+                    .map(|&[x, y]| {
+                        let row = row_starts[y.into_usize()]..row_starts[y.into_usize() + 1];
+                        let index =
+                            row.start + vertices[row].partition_point(|&[other_x, _]| other_x < x);
+                        P::from_usize(index)
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    (vertices, rings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::tests::{canonical, pixels},
+        Algorithm, Args,
+    };
+
+    fn rings(shapes: &Shapes<u16, u16>) -> Vec<Vec<[u16; 2]>> {
+        canonical(shapes.rings().map(|ring| {
+            ring.iter()
+                .map(|&index| shapes.vertices[usize::from(index)])
+                .collect()
+        }))
+    }
+
+    #[test]
+    fn stitched_tiles_match_whole_image() {
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            for (seed, (width, height, size)) in (1..).zip([
+                (5, 5, [5, 5]),
+                (13, 9, [4, 4]),
+                (20, 17, [6, 3]),
+                (9, 16, [1, 2]),
+            ]) {
+                let pixels = pixels(seed, width * height);
+                let mut tiles = Tiles::with_size(size);
+                for index_y in 0..height.div_ceil(size[1]) {
+                    for index_x in 0..width.div_ceil(size[0]) {
+                        let [x, y] = [index_x * size[0], index_y * size[1]];
+                        let [tile_width, tile_height] =
+                            [size[0].min(width - x), size[1].min(height - y)];
+                        let tile_pixels = (y..y + tile_height).flat_map(|y| {
+                            pixels[y * width + x..y * width + x + tile_width]
+                                .iter()
+                                .copied()
+                        });
+                        let args = Args::new(tile_width, tile_height, tile_pixels);
+                        tiles.insert(algorithm.tile(args, size, [index_x, index_y]));
+                    }
+                }
+                let winding = Winding::CounterClockwise;
+                let whole =
+                    algorithm.shapes(Args::new(width, height, pixels.iter().copied()), winding);
+                assert_eq!(
+                    rings(&tiles.shapes(winding)),
+                    rings(&whole),
+                    "{width}x{height} in {size:?} {connectivity:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,848 @@
+use crate::{
+    core::Primitive,
+    shapes::{Shape, Shapes},
+};
+use ::alloc::vec::Vec;
+use ::core::{cmp::Ordering, iter};
+
+// Vertices of pixel shapes have the same type as indices.
+#[allow(clippy::mismatching_type_param_order)]
+impl<P: Primitive> Shapes<P, P> {
+    /// Splits every shape into triangles wound the same way as its outer ring, three indices
+    /// into [`Self::vertices`] per triangle, with coordinates above `2^53` rounded.
+    ///
+    /// # Panics
+    /// If there are more vertices than [`u32`] can index
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn triangulate(&self) -> Vec<u32> {
+        triangulate(&self.shapes, |index| {
+            self.vertices[index].map(|coordinate| coordinate.into_usize() as f64)
+        })
+    }
+}
+
+impl<P: Primitive> Shapes<f32, P> {
+    /// Same as [`Shapes::triangulate()`] of pixel shapes.
+    ///
+    /// # Panics
+    /// If there are more vertices than [`u32`] can index
+    #[must_use]
+    pub fn triangulate(&self) -> Vec<u32> {
+        triangulate(&self.shapes, |index| self.vertices[index].map(f64::from))
+    }
+}
+
+/// Offset of vertices a ring goes through more than once, small enough to keep pixel
+/// coordinates exact.
+const PINCH_OFFSET: f64 = 1.0 / 1024.0;
+
+pub(crate) fn triangulate<P: Primitive>(
+    shapes: &[Shape<P>],
+    vertex: impl Fn(usize) -> [f64; 2],
+) -> Vec<u32> {
+    let mut triangulator = Triangulator::default();
+    let mut pinches = Vec::new();
+    for shape in shapes {
+        triangulator.nodes.clear();
+        // Rings touch themselves and each other at saddles, every visit of such a vertex is
+        // moved between its two edges so that the rings become simple.
+        pinches.clear();
+        pinches.extend(
+            iter::once(&shape.outer)
+                .chain(&shape.holes)
+                .flat_map(|ring| ring.iter().copied()),
+        );
+        pinches.sort_unstable();
+        let pinches: Vec<P> = pinches
+            .windows(2)
+            .filter_map(|pair| (pair[0] == pair[1]).then_some(pair[0]))
+            .collect();
+        let ring = |ring: &[P]| -> Vec<(u32, [f64; 2])> {
+            (0..ring.len())
+                .map(|position| {
+                    let index = ring[position];
+                    let vertex_index =
+                        u32::try_from(index.into_usize()).expect("too many vertices");
+                    let point @ [x, y] = vertex(index.into_usize());
+                    if pinches.binary_search(&index).is_err() {
+                        return (vertex_index, point);
+                    }
+                    let prev = ring[(position + ring.len() - 1) % ring.len()];
+                    let next = ring[(position + 1) % ring.len()];
+                    let [dx, dy] = [prev, next]
+                        .map(|neighbour| {
+                            let [neighbour_x, neighbour_y] = vertex(neighbour.into_usize());
+                            let [dx, dy] = [neighbour_x - x, neighbour_y - y];
+                            let len = dx.abs().max(dy.abs());
+                            [dx / len, dy / len]
+                        })
+                        .into_iter()
+                        .fold([0.0, 0.0], |[x, y], [dx, dy]| [x + dx, y + dy]);
+                    (vertex_index, [x + dx * PINCH_OFFSET, y + dy * PINCH_OFFSET])
+                })
+                .collect()
+        };
+        let outer = ring(&shape.outer);
+        let is_reversed = signed_area(&outer) <= 0.0;
+        let Some(mut start) = triangulator.linked_list(&outer, true) else {
+            continue;
+        };
+        if triangulator.next(start) == triangulator.prev(start) {
+            continue;
+        }
+        if !shape.holes.is_empty() {
+            let holes: Vec<usize> = shape
+                .holes
+                .iter()
+                .filter_map(|hole| triangulator.linked_list(&ring(hole), false))
+                .collect();
+            start = triangulator.eliminate_holes(holes, start);
+        }
+        triangulator.hash_points(start);
+        let first = triangulator.triangles.len();
+        triangulator.earcut(start, Pass::Ears);
+        if is_reversed {
+            for triangle in triangulator.triangles[first..].chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+    // Slivers between the moved visits of a vertex are empty once they are moved back.
+    let mut triangles = triangulator.triangles;
+    let mut len = 0;
+    for index in (0..triangles.len()).step_by(3) {
+        let [[ax, ay], [bx, by], [cx, cy]] =
+            [0, 1, 2].map(|offset| vertex(triangles[index + offset] as usize));
+        if (bx - ax) * (cy - ay) - (by - ay) * (cx - ax) != 0.0 {
+            triangles.copy_within(index..index + 3, len);
+            len += 3;
+        }
+    }
+    triangles.truncate(len);
+    triangles
+}
+
+/// Twice the signed area of `ring`, positive for counter-clockwise rings in a frame where `y`
+/// points up.
+fn signed_area(ring: &[(u32, [f64; 2])]) -> f64 {
+    let mut sum = 0.0;
+    for (index, &(_, [x, y])) in ring.iter().enumerate() {
+        let (_, [previous_x, previous_y]) = ring[(index + ring.len() - 1) % ring.len()];
+        sum += (previous_x - x) * (y + previous_y);
+    }
+    sum
+}
+
+const fn point_in_triangle(
+    [ax, ay]: [f64; 2],
+    [bx, by]: [f64; 2],
+    [cx, cy]: [f64; 2],
+    [px, py]: [f64; 2],
+) -> bool {
+    (cx - px) * (ay - py) >= (ax - px) * (cy - py)
+        && (ax - px) * (by - py) >= (bx - px) * (ay - py)
+        && (bx - px) * (cy - py) >= (cx - px) * (by - py)
+}
+
+/// Whether `q` lies on the bounding box of the segment from `p` to `r`.
+fn on_segment([px, py]: [f64; 2], [qx, qy]: [f64; 2], [rx, ry]: [f64; 2]) -> bool {
+    qx <= px.max(rx) && qx >= px.min(rx) && qy <= py.max(ry) && qy >= py.min(ry)
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Node {
+    index: u32,
+    /// Node of the input rings this one is, or is a copy of, since visits of a vertex moved
+    /// apart have the same index.
+    key: usize,
+    point: [f64; 2],
+    prev: usize,
+    next: usize,
+    z: u32,
+    prev_z: Option<usize>,
+    next_z: Option<usize>,
+}
+
+/// What to try once no ear is left to clip.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum Pass {
+    Ears,
+    /// Drops collinear and repeated vertices.
+    Filtered,
+    /// Clips triangles around local self-intersections, like rings touching themselves at a
+    /// vertex.
+    Cured,
+}
+
+/// Ear clipping over circular lists of ring vertices, following `earcut`.
+///
+/// Pixel coordinates are small integers, so every test is exact and collinear staircase steps
+/// are dropped before they can produce empty triangles.
+#[derive(Clone, Debug, Default)]
+struct Triangulator {
+    nodes: Vec<Node>,
+    triangles: Vec<u32>,
+    /// Corner and scale mapping points to [`Self::z_order()`], zero for small rings checked
+    /// vertex by vertex.
+    min: [f64; 2],
+    inv_size: f64,
+}
+
+// Points are pixel coordinates or pinch offsets of them, exact in `f64`, so they are compared
+// exactly like `earcut` does.
+#[allow(clippy::float_cmp)]
+impl Triangulator {
+    fn point(&self, node: usize) -> [f64; 2] {
+        self.nodes[node].point
+    }
+
+    fn prev(&self, node: usize) -> usize {
+        self.nodes[node].prev
+    }
+
+    fn next(&self, node: usize) -> usize {
+        self.nodes[node].next
+    }
+
+    fn index(&self, node: usize) -> u32 {
+        self.nodes[node].index
+    }
+
+    fn key(&self, node: usize) -> usize {
+        self.nodes[node].key
+    }
+
+    /// Twice the signed area of the triangle, negative when it turns the way of outer rings.
+    fn area(&self, p: usize, q: usize, r: usize) -> f64 {
+        let ([px, py], [qx, qy], [rx, ry]) = (self.point(p), self.point(q), self.point(r));
+        (qy - py) * (rx - qx) - (qx - px) * (ry - qy)
+    }
+
+    fn equals(&self, a: usize, b: usize) -> bool {
+        self.point(a) == self.point(b)
+    }
+
+    fn insert(&mut self, (index, point): (u32, [f64; 2]), last: Option<usize>) -> usize {
+        let node = self.nodes.len();
+        let (prev, next) = match last {
+            Some(last) => {
+                let next = self.next(last);
+                self.nodes[next].prev = node;
+                self.nodes[last].next = node;
+                (last, next)
+            }
+            None => (node, node),
+        };
+        self.nodes.push(Node {
+            index,
+            key: node,
+            point,
+            prev,
+            next,
+            z: 0,
+            prev_z: None,
+            next_z: None,
+        });
+        node
+    }
+
+    fn remove(&mut self, node: usize) {
+        let Node {
+            prev,
+            next,
+            prev_z,
+            next_z,
+            ..
+        } = self.nodes[node];
+        self.nodes[next].prev = prev;
+        self.nodes[prev].next = next;
+        if let Some(prev_z) = prev_z {
+            self.nodes[prev_z].next_z = next_z;
+        }
+        if let Some(next_z) = next_z {
+            self.nodes[next_z].prev_z = prev_z;
+        }
+    }
+
+    /// Enables looking up ear candidates by z-order for rings with more than 80 vertices, like
+    /// `earcut` does.
+    fn hash_points(&mut self, start: usize) {
+        let (mut node, mut len) = (start, 0);
+        let (mut min, mut max) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+        loop {
+            let [x, y] = self.point(node);
+            (min, max) = (
+                [min[0].min(x), min[1].min(y)],
+                [max[0].max(x), max[1].max(y)],
+            );
+            len += 1;
+            node = self.next(node);
+            if node == start {
+                break;
+            }
+        }
+        let size = (max[0] - min[0]).max(max[1] - min[1]);
+        self.min = min;
+        self.inv_size = if len > 80 && size > 0.0 {
+            32767.0 / size
+        } else {
+            0.0
+        };
+    }
+
+    /// Interleaved bits of the coordinates above [`Self::min`] scaled to 15 bits.
+    #[allow(clippy::cast_sign_loss)]
+    fn z_order(&self, [x, y]: [f64; 2]) -> u32 {
+        let spread = |coordinate: f64| {
+            let mut bits = coordinate as u32;
+            bits = (bits | (bits << 8)) & 0x00FF_00FF;
+            bits = (bits | (bits << 4)) & 0x0F0F_0F0F;
+            bits = (bits | (bits << 2)) & 0x3333_3333;
+            (bits | (bits << 1)) & 0x5555_5555
+        };
+        spread((x - self.min[0]) * self.inv_size) | (spread((y - self.min[1]) * self.inv_size) << 1)
+    }
+
+    /// Links the nodes of the ring in z-order.
+    fn index_curve(&mut self, start: usize) {
+        let mut nodes = Vec::new();
+        let mut node = start;
+        loop {
+            self.nodes[node].z = self.z_order(self.point(node));
+            nodes.push(node);
+            node = self.next(node);
+            if node == start {
+                break;
+            }
+        }
+        nodes.sort_by_key(|&node| self.nodes[node].z);
+        for (position, &node) in nodes.iter().enumerate() {
+            self.nodes[node].prev_z = position.checked_sub(1).map(|position| nodes[position]);
+            self.nodes[node].next_z = nodes.get(position + 1).copied();
+        }
+    }
+
+    /// Links `ring` so that it turns the way of outer rings, or the other way for holes.
+    fn linked_list(&mut self, ring: &[(u32, [f64; 2])], is_outer: bool) -> Option<usize> {
+        let mut last = None;
+        if is_outer == (signed_area(ring) > 0.0) {
+            for &vertex in ring {
+                last = Some(self.insert(vertex, last));
+            }
+        } else {
+            for &vertex in ring.iter().rev() {
+                last = Some(self.insert(vertex, last));
+            }
+        }
+        let last = last?;
+        if self.equals(last, self.next(last)) {
+            let next = self.next(last);
+            self.remove(last);
+            return Some(next);
+        }
+        Some(last)
+    }
+
+    /// Removes repeated and collinear vertices, returns one of the ones left.
+    fn filter_points(&mut self, start: usize, end: Option<usize>) -> usize {
+        let (mut node, mut end) = (start, end.unwrap_or(start));
+        loop {
+            let next = self.next(node);
+            if self.equals(node, next) || self.area(self.prev(node), node, next) == 0.0 {
+                self.remove(node);
+                node = self.prev(node);
+                end = node;
+                if node == self.next(node) {
+                    break;
+                }
+            } else {
+                node = next;
+                if node == end {
+                    break;
+                }
+            }
+        }
+        end
+    }
+
+    fn earcut(&mut self, mut ear: usize, pass: Pass) {
+        if pass == Pass::Ears && self.inv_size != 0.0 {
+            self.index_curve(ear);
+        }
+        let mut stop = ear;
+        while self.prev(ear) != self.next(ear) {
+            let (prev, next) = (self.prev(ear), self.next(ear));
+            if self.is_ear(ear) {
+                self.triangles
+                    .extend([self.index(prev), self.index(ear), self.index(next)]);
+                self.remove(ear);
+                ear = self.next(next);
+                stop = ear;
+                continue;
+            }
+            ear = next;
+            if ear == stop {
+                match pass {
+                    Pass::Ears => {
+                        let ear = self.filter_points(ear, None);
+                        self.earcut(ear, Pass::Filtered);
+                    }
+                    Pass::Filtered => {
+                        let ear = self.filter_points(ear, None);
+                        let ear = self.cure_local_intersections(ear);
+                        self.earcut(ear, Pass::Cured);
+                    }
+                    Pass::Cured => self.split(ear),
+                }
+                break;
+            }
+        }
+    }
+
+    fn is_ear(&self, ear: usize) -> bool {
+        let (a, b, c) = (self.prev(ear), ear, self.next(ear));
+        if self.area(a, b, c) >= 0.0 {
+            return false;
+        }
+        let (pa, pb, pc) = (self.point(a), self.point(b), self.point(c));
+        let (min_x, max_x) = (pa[0].min(pb[0]).min(pc[0]), pa[0].max(pb[0]).max(pc[0]));
+        let (min_y, max_y) = (pa[1].min(pb[1]).min(pc[1]), pa[1].max(pb[1]).max(pc[1]));
+        let blocks = |node: usize| {
+            let point @ [x, y] = self.point(node);
+            node != a
+                && node != c
+                && (min_x..=max_x).contains(&x)
+                && (min_y..=max_y).contains(&y)
+                && point != pa
+                && point_in_triangle(pa, pb, pc, point)
+                && self.area(self.prev(node), node, self.next(node)) >= 0.0
+        };
+        if self.inv_size == 0.0 {
+            let mut node = self.next(c);
+            while node != a {
+                if blocks(node) {
+                    return false;
+                }
+                node = self.next(node);
+            }
+            return true;
+        }
+        // Only vertices between the z-orders of the corners of the bounding box can be inside.
+        let (min_z, max_z) = (self.z_order([min_x, min_y]), self.z_order([max_x, max_y]));
+        let (mut prev_z, mut next_z) = (self.nodes[ear].prev_z, self.nodes[ear].next_z);
+        while let Some(node) = prev_z.filter(|&node| self.nodes[node].z >= min_z) {
+            if blocks(node) {
+                return false;
+            }
+            prev_z = self.nodes[node].prev_z;
+        }
+        while let Some(node) = next_z.filter(|&node| self.nodes[node].z <= max_z) {
+            if blocks(node) {
+                return false;
+            }
+            next_z = self.nodes[node].next_z;
+        }
+        true
+    }
+
+    fn cure_local_intersections(&mut self, mut start: usize) -> usize {
+        let mut node = start;
+        loop {
+            let (a, b) = (self.prev(node), self.next(self.next(node)));
+            if !self.equals(a, b)
+                && self.intersects(a, node, self.next(node), b)
+                && self.locally_inside(a, b)
+                && self.locally_inside(b, a)
+            {
+                self.triangles
+                    .extend([self.index(a), self.index(node), self.index(b)]);
+                let next = self.next(node);
+                self.remove(node);
+                self.remove(next);
+                (node, start) = (b, b);
+            }
+            node = self.next(node);
+            if node == start {
+                break;
+            }
+        }
+        self.filter_points(node, None)
+    }
+
+    /// Splits the ring in two along a diagonal and clips both halves.
+    fn split(&mut self, start: usize) {
+        let mut a = start;
+        loop {
+            let mut b = self.next(self.next(a));
+            while b != self.prev(a) {
+                if self.key(a) != self.key(b) && self.is_valid_diagonal(a, b) {
+                    let c = self.split_polygon(a, b);
+                    let a = self.filter_points(a, Some(self.next(a)));
+                    let c = self.filter_points(c, Some(self.next(c)));
+                    self.earcut(a, Pass::Ears);
+                    self.earcut(c, Pass::Ears);
+                    return;
+                }
+                b = self.next(b);
+            }
+            a = self.next(a);
+            if a == start {
+                break;
+            }
+        }
+    }
+
+    /// Joins holes to the outer ring from left to right, each with a pair of bridge edges.
+    fn eliminate_holes(&mut self, holes: Vec<usize>, mut outer: usize) -> usize {
+        let mut holes: Vec<usize> = holes.into_iter().map(|hole| self.leftmost(hole)).collect();
+        holes.sort_by(|&a, &b| {
+            let ([ax, ay], [bx, by]) = (self.point(a), self.point(b));
+            ax.total_cmp(&bx).then(ay.total_cmp(&by)).then_with(|| {
+                let slope = |node: usize| {
+                    let ([x, y], [next_x, next_y]) =
+                        (self.point(node), self.point(self.next(node)));
+                    (next_y - y) / (next_x - x)
+                };
+                slope(a).partial_cmp(&slope(b)).unwrap_or(Ordering::Equal)
+            })
+        });
+        for hole in holes {
+            outer = self.eliminate_hole(hole, outer);
+        }
+        outer
+    }
+
+    fn eliminate_hole(&mut self, hole: usize, outer: usize) -> usize {
+        let Some(bridge) = self.find_hole_bridge(hole, outer) else {
+            return outer;
+        };
+        let bridge_reverse = self.split_polygon(bridge, hole);
+        self.filter_points(bridge_reverse, Some(self.next(bridge_reverse)));
+        self.filter_points(bridge, Some(self.next(bridge)))
+    }
+
+    /// Vertex of the outer ring that the leftmost vertex of `hole` can see.
+    fn find_hole_bridge(&self, hole: usize, outer: usize) -> Option<usize> {
+        // The ray can end at a copy of a vertex facing away from the hole, like one left by the
+        // bridge of another hole, then the closest vertex it can see is joined instead.
+        match self.cast_hole_bridge(hole, outer) {
+            Some(bridge)
+                if self.equals(bridge, hole)
+                    || (self.locally_inside(bridge, hole) && self.locally_inside(hole, bridge)) =>
+            {
+                Some(bridge)
+            }
+            _ => {
+                let mut nodes = Vec::new();
+                let mut node = outer;
+                loop {
+                    nodes.push(node);
+                    node = self.next(node);
+                    if node == outer {
+                        break;
+                    }
+                }
+                let [hole_x, hole_y] = self.point(hole);
+                nodes.sort_by(|&a, &b| {
+                    let distance = |node: usize| {
+                        let [x, y] = self.point(node);
+                        (x - hole_x) * (x - hole_x) + (y - hole_y) * (y - hole_y)
+                    };
+                    distance(a).total_cmp(&distance(b))
+                });
+                nodes
+                    .into_iter()
+                    .find(|&node| self.is_valid_bridge(node, hole))
+            }
+        }
+    }
+
+    /// Whether the bridge from `bridge` to `hole` starts inside both rings and crosses none of
+    /// their edges.
+    fn is_valid_bridge(&self, bridge: usize, hole: usize) -> bool {
+        if self.equals(bridge, hole) {
+            return true;
+        }
+        if !self.locally_inside(bridge, hole) || !self.locally_inside(hole, bridge) {
+            return false;
+        }
+        [bridge, hole].into_iter().all(|start| {
+            let mut node = start;
+            loop {
+                let next = self.next(node);
+                let touches = [node, next]
+                    .into_iter()
+                    .any(|node| self.equals(node, bridge) || self.equals(node, hole));
+                if !touches && self.intersects(node, next, bridge, hole) {
+                    return false;
+                }
+                node = next;
+                if node == start {
+                    return true;
+                }
+            }
+        })
+    }
+
+    /// Vertex found by casting a ray to the left of the leftmost vertex of `hole`, following
+    /// `earcut`.
+    fn cast_hole_bridge(&self, hole: usize, outer: usize) -> Option<usize> {
+        let [hole_x, hole_y] = self.point(hole);
+        let (mut node, mut nearest_x, mut bridge) = (outer, f64::NEG_INFINITY, None);
+        if self.equals(hole, node) {
+            return Some(node);
+        }
+        // Closest edge crossed by a ray going left from the hole, joined at its left end.
+        loop {
+            let next = self.next(node);
+            if self.equals(hole, next) {
+                return Some(next);
+            }
+            let ([x, y], [next_x, next_y]) = (self.point(node), self.point(next));
+            if hole_y <= y && hole_y >= next_y && next_y != y {
+                let crossing_x = x + (hole_y - y) * (next_x - x) / (next_y - y);
+                if crossing_x <= hole_x && crossing_x > nearest_x {
+                    nearest_x = crossing_x;
+                    bridge = Some(if x < next_x { node } else { next });
+                    if crossing_x == hole_x {
+                        return bridge;
+                    }
+                }
+            }
+            node = next;
+            if node == outer {
+                break;
+            }
+        }
+        // Vertices inside the triangle of the hole, the crossing and the end can block the
+        // bridge, the one closest in angle to the ray is joined instead.
+        let mut bridge = bridge?;
+        let (stop, [bridge_x, bridge_y]) = (bridge, self.point(bridge));
+        let mut tan_min = f64::INFINITY;
+        node = bridge;
+        loop {
+            let point @ [x, y] = self.point(node);
+            let (left, right) = if hole_y < bridge_y {
+                (hole_x, nearest_x)
+            } else {
+                (nearest_x, hole_x)
+            };
+            if hole_x >= x
+                && x >= bridge_x
+                && hole_x != x
+                && point_in_triangle([left, hole_y], [bridge_x, bridge_y], [right, hole_y], point)
+            {
+                let tan = (hole_y - y).abs() / (hole_x - x);
+                let current_x = self.point(bridge)[0];
+                if self.locally_inside(node, hole)
+                    && (tan < tan_min
+                        || (tan == tan_min
+                            && (x > current_x
+                                || (x == current_x && self.sector_contains_sector(bridge, node)))))
+                {
+                    bridge = node;
+                    tan_min = tan;
+                }
+            }
+            node = self.next(node);
+            if node == stop {
+                break;
+            }
+        }
+        Some(bridge)
+    }
+
+    fn sector_contains_sector(&self, m: usize, p: usize) -> bool {
+        self.area(self.prev(m), m, self.prev(p)) < 0.0
+            && self.area(self.next(p), m, self.next(m)) < 0.0
+    }
+
+    fn leftmost(&self, start: usize) -> usize {
+        let (mut node, mut leftmost) = (start, start);
+        loop {
+            let ([x, y], [leftmost_x, leftmost_y]) = (self.point(node), self.point(leftmost));
+            if x < leftmost_x || (x == leftmost_x && y < leftmost_y) {
+                leftmost = node;
+            }
+            node = self.next(node);
+            if node == start {
+                break;
+            }
+        }
+        leftmost
+    }
+
+    fn is_valid_diagonal(&self, a: usize, b: usize) -> bool {
+        let (prev_a, next_a) = (self.prev(a), self.next(a));
+        self.key(next_a) != self.key(b)
+            && self.key(prev_a) != self.key(b)
+            && !self.intersects_polygon(a, b)
+            && ((self.locally_inside(a, b)
+                && self.locally_inside(b, a)
+                && self.middle_inside(a, b)
+                && (self.area(prev_a, a, self.prev(b)) != 0.0
+                    || self.area(a, self.prev(b), b) != 0.0))
+                || (self.equals(a, b)
+                    && self.area(prev_a, a, next_a) > 0.0
+                    && self.area(self.prev(b), b, self.next(b)) > 0.0))
+    }
+
+    fn intersects(&self, p1: usize, q1: usize, p2: usize, q2: usize) -> bool {
+        let sign = |area: f64| {
+            if area > 0.0 {
+                1
+            } else if area < 0.0 {
+                -1
+            } else {
+                0
+            }
+        };
+        let o1 = sign(self.area(p1, q1, p2));
+        let o2 = sign(self.area(p1, q1, q2));
+        let o3 = sign(self.area(p2, q2, p1));
+        let o4 = sign(self.area(p2, q2, q1));
+        let ([p1, q1], [p2, q2]) = (
+            [p1, q1].map(|node| self.point(node)),
+            [p2, q2].map(|node| self.point(node)),
+        );
+        (o1 != o2 && o3 != o4)
+            || (o1 == 0 && on_segment(p1, p2, q1))
+            || (o2 == 0 && on_segment(p1, q2, q1))
+            || (o3 == 0 && on_segment(p2, p1, q2))
+            || (o4 == 0 && on_segment(p2, q1, q2))
+    }
+
+    fn intersects_polygon(&self, a: usize, b: usize) -> bool {
+        let (a_key, b_key) = (self.key(a), self.key(b));
+        let mut node = a;
+        loop {
+            let next = self.next(node);
+            let keys = [self.key(node), self.key(next)];
+            if !keys.contains(&a_key) && !keys.contains(&b_key) && self.intersects(node, next, a, b)
+            {
+                return true;
+            }
+            node = next;
+            if node == a {
+                return false;
+            }
+        }
+    }
+
+    /// Whether the diagonal from `a` to `b` starts inside the ring.
+    fn locally_inside(&self, a: usize, b: usize) -> bool {
+        let (prev, next) = (self.prev(a), self.next(a));
+        if self.area(prev, a, next) < 0.0 {
+            self.area(a, b, next) >= 0.0 && self.area(a, prev, b) >= 0.0
+        } else {
+            self.area(a, b, prev) < 0.0 || self.area(a, next, b) < 0.0
+        }
+    }
+
+    fn middle_inside(&self, a: usize, b: usize) -> bool {
+        let ([ax, ay], [bx, by]) = (self.point(a), self.point(b));
+        let [middle_x, middle_y] = [f64::midpoint(ax, bx), f64::midpoint(ay, by)];
+        let (mut node, mut is_inside) = (a, false);
+        loop {
+            let next = self.next(node);
+            let ([x, y], [next_x, next_y]) = (self.point(node), self.point(next));
+            if (y > middle_y) != (next_y > middle_y)
+                && next_y != y
+                && middle_x < (next_x - x) * (middle_y - y) / (next_y - y) + x
+            {
+                is_inside = !is_inside;
+            }
+            node = next;
+            if node == a {
+                return is_inside;
+            }
+        }
+    }
+
+    /// Links `a` to `b` with a pair of edges, splitting the ring in two, and returns the copy
+    /// of `b` starting the other half.
+    fn split_polygon(&mut self, a: usize, b: usize) -> usize {
+        let (a_copy, b_copy) = (self.nodes.len(), self.nodes.len() + 1);
+        let (a_next, b_prev) = (self.next(a), self.prev(b));
+        self.nodes.push(Node {
+            prev: b_copy,
+            next: a_next,
+            prev_z: None,
+            next_z: None,
+            ..self.nodes[a]
+        });
+        self.nodes.push(Node {
+            prev: b_prev,
+            next: a_copy,
+            prev_z: None,
+            next_z: None,
+            ..self.nodes[b]
+        });
+        self.nodes[a].next = b;
+        self.nodes[b].prev = a;
+        self.nodes[a_next].prev = a_copy;
+        self.nodes[b_prev].next = b_copy;
+        b_copy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{tests::pixels, Connectivity},
+        Algorithm, Args, Winding,
+    };
+    use ::alloc::vec::Vec;
+
+    #[test]
+    fn triangles_wind_like_outer_rings_and_cover_pixels() {
+        for (connectivity, winding) in [
+            (Connectivity::Four, Winding::CounterClockwise),
+            (Connectivity::Eight, Winding::CounterClockwise),
+            (Connectivity::Four, Winding::Clockwise),
+            (Connectivity::Eight, Winding::Clockwise),
+        ] {
+            let mut algorithm = Algorithm::new().with_connectivity(connectivity);
+            for (seed, (width, height)) in (1..).zip([(1, 1), (3, 2), (8, 8), (17, 5), (32, 32)]) {
+                let pixels = pixels(seed, width * height);
+                let shapes =
+                    algorithm.shapes(Args::new(width, height, pixels.iter().copied()), winding);
+                let vertex = |index: usize| shapes.vertices[index].map(i64::from);
+                let twice_area = |ring: &mut dyn Iterator<Item = usize>| {
+                    let ring: Vec<_> = ring.map(vertex).collect();
+                    ring.iter()
+                        .zip(ring.iter().cycle().skip(1))
+                        .map(|([x0, y0], [x1, y1])| x0 * y1 - x1 * y0)
+                        .sum::<i64>()
+                };
+                let signs: Vec<_> = shapes
+                    .shapes
+                    .iter()
+                    .map(|shape| {
+                        twice_area(&mut shape.outer.iter().map(|&index| usize::from(index)))
+                            .signum()
+                    })
+                    .collect();
+                let sign = signs.first().copied().unwrap_or(1);
+                assert!(signs.iter().all(|&other| other == sign));
+                let mut area = 0;
+                for triangle in shapes.triangulate().chunks_exact(3) {
+                    let triangle_area =
+                        sign * twice_area(&mut triangle.iter().map(|&index| index as usize));
+                    assert!(
+                        triangle_area > 0,
+                        "{triangle:?} {connectivity:?} {winding:?}"
+                    );
+                    area += triangle_area;
+                }
+                let count: i64 = pixels.iter().map(|&pixel| i64::from(pixel)).sum();
+                assert_eq!(
+                    area,
+                    2 * count,
+                    "{width}x{height} {connectivity:?} {winding:?}"
+                );
+            }
+        }
+    }
+}